@@ -25,11 +25,41 @@
 
 use std::any::TypeId;
 
+use ratatui::layout::{Position, Rect};
+
 /// The cursor is on file list.
 pub struct Files;
 /// the cursor is on text view.
 pub struct View;
 
+/// Where the panes were last drawn, to tell what the mouse is over.
+#[derive(Default, Clone, Copy)]
+pub struct Panes {
+    /// The file list along with its border.
+    pub files: Rect,
+    /// The entries of the file list.
+    pub list: Rect,
+    /// The text view along with its border.
+    pub text: Rect,
+}
+
+impl Panes {
+    /// Whether the point is on the right border of the text view, where the scrollbar is.
+    pub fn on_scrollbar(&self, column: u16, row: u16) -> bool {
+        self.text.contains(Position::new(column, row))
+            && column + 1 == self.text.right()
+            && row > self.text.top()
+            && row + 1 < self.text.bottom()
+    }
+
+    /// How far down the scrollbar the row is, from zero to one.
+    pub fn scrollbar_ratio(&self, row: u16) -> f64 {
+        let top = self.text.top() + 1;
+        let height = self.text.height.saturating_sub(3).max(1);
+        f64::from(row.saturating_sub(top).min(height)) / f64::from(height)
+    }
+}
+
 /// To denote where the current cursor is located.
 pub struct KeyboardCursor {
     toggle: Option<TypeId>,
@@ -0,0 +1,111 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2024 Mohammed Rehaan and contributors
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ * */
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+
+use crate::io::{BufLine, SKIPPED};
+use crate::reader::{clean, INDEX_STRIDE};
+
+/// Sparse index of line start offsets for files too large to keep in memory.
+///
+/// Only the offset of every [`INDEX_STRIDE`]-th line is kept, lines in between
+/// are found by reading forward from the closest checkpoint.
+pub struct LineIndex {
+    file: BufReader<File>,
+    checkpoints: Vec<u64>,
+    lines: usize,
+    // Line number preceding the first line, so numbering carries on after a restart.
+    base: usize,
+    // Shown above the content after the file was rotated or truncated.
+    head: Option<&'static str>,
+}
+
+impl LineIndex {
+    /// Index `file` from `start` onwards, which is expected to be the start of a line.
+    pub fn new(file: File, start: u64) -> Self {
+        LineIndex {
+            file: BufReader::new(file),
+            checkpoints: vec![start],
+            lines: 0,
+            base: 0,
+            head: (start > 0).then_some(SKIPPED),
+        }
+    }
+
+    pub fn extend(&mut self, lines: usize, checkpoints: Vec<u64>) {
+        self.lines += lines;
+        self.checkpoints.extend(checkpoints);
+    }
+
+    pub fn restart(&mut self, file: File, marker: &'static str) {
+        self.base += self.lines;
+        *self = LineIndex {
+            head: Some(marker),
+            base: self.base,
+            ..LineIndex::new(file, 0)
+        };
+    }
+
+    /// Number of entries including the marker.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lines + self.head.is_some() as usize
+    }
+
+    /// Read `count` entries starting at `start` from the disk.
+    pub fn read(&mut self, start: usize, count: usize) -> anyhow::Result<Vec<BufLine>> {
+        let mut window = Vec::new();
+        let mut count = count.min(self.len().saturating_sub(start));
+
+        let head = self.head.is_some() as usize;
+        if let Some(marker) = self.head.filter(|_| start == 0 && count > 0) {
+            window.push(BufLine::marker(marker));
+            count -= 1;
+        }
+        if count == 0 {
+            return Ok(window);
+        }
+
+        let first = start.saturating_sub(head);
+        self.file
+            .seek(SeekFrom::Start(self.checkpoints[first / INDEX_STRIDE]))?;
+
+        let mut line = Vec::new();
+        for number in (first - first % INDEX_STRIDE)..(first + count) {
+            line.clear();
+            if self.file.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            if number < first {
+                continue;
+            }
+            let text = clean(&String::from_utf8_lossy(&line));
+            window.push(BufLine::content(self.base + number + 1, text));
+        }
+
+        Ok(window)
+    }
+}
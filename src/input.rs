@@ -27,20 +27,37 @@ use std::fs::read_dir;
 
 use anyhow::anyhow;
 use crossterm::event::*;
+use ratatui::layout::Position;
 use ratatui::widgets::*;
 
 use crate::cursor::*;
 use crate::resource::*;
 use crate::*;
 use io::FileBuf;
-use utils::complete;
+use search::SearchMode;
+use status::Notice;
+use utils::{complete, split_lines_arg, split_watch_arg};
 
 pub const DELTA: u64 = 16;
+// Lines scrolled by a turn of the mouse wheel.
+const WHEEL_LINES: usize = 3;
+
+/// What the text typed into the entry box is used for.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Prompt {
+    #[default]
+    File,
+    Search(SearchMode),
+    Filter(SearchMode),
+    Time,
+    Goto,
+}
 
 #[derive(Default)]
 pub struct EntryBox {
     is_active: bool,
     is_err: bool,
+    prompt: Prompt,
     input_buff: String,
 }
 
@@ -59,15 +76,28 @@ impl EntryBox {
         self.is_active
     }
 
+    pub fn set_prompt(&mut self, prompt: Prompt) {
+        self.prompt = prompt;
+    }
+
+    #[inline]
+    pub fn prompt(&self) -> Prompt {
+        self.prompt
+    }
+
     pub fn push(&mut self, ch: char) {
         self.input_buff.push(ch);
     }
 
     pub fn complete(&mut self) {
-        let mut new_buff = if self.input_buff.starts_with("/") {
-            self.input_buff.to_string()
+        // Only the path is completed, a leading `-n <N>` is kept as is.
+        let (_, input) = split_lines_arg(&self.input_buff);
+        let args = self.input_buff[..self.input_buff.len() - input.len()].to_string();
+
+        let mut new_buff = if input.starts_with("/") {
+            input.to_string()
         } else {
-            "./".to_string() + &self.input_buff
+            "./".to_string() + input
         };
 
         let path: Vec<&str> = new_buff.split("/").collect();
@@ -95,7 +125,7 @@ impl EntryBox {
             None => new_buff,
         };
 
-        self.input_buff = new_buff;
+        self.input_buff = args + &new_buff;
     }
 
     pub fn pop(&mut self) {
@@ -116,6 +146,10 @@ impl EntryBox {
         self.input_buff.clear();
     }
 
+    pub fn set(&mut self, input: &str) {
+        self.input_buff = input.to_string();
+    }
+
     #[inline]
     pub fn take(&mut self) -> String {
         std::mem::take(&mut self.input_buff)
@@ -171,8 +205,11 @@ impl FileListState {
         self.size = size;
     }
 
-    pub fn next(&mut self) {
-        self.index = bounded_add(self.index, 1, self.size.saturating_sub(1));
+    pub fn next(&mut self, times: usize) {
+        self.index = self
+            .index
+            .saturating_add(times)
+            .min(self.size.saturating_sub(1));
         self.state.select(Some(self.index));
     }
 
@@ -181,8 +218,8 @@ impl FileListState {
         self.state.select(Some(self.index));
     }
 
-    pub fn prev(&mut self) {
-        self.index = self.index.saturating_sub(1);
+    pub fn prev(&mut self, times: usize) {
+        self.index = self.index.saturating_sub(times);
         self.state.select(Some(self.index));
     }
 
@@ -191,6 +228,19 @@ impl FileListState {
         self.state.select(Some(self.index));
     }
 
+    pub fn select(&mut self, index: usize) {
+        if index < self.size {
+            self.index = index;
+            self.state.select(Some(self.index));
+        }
+    }
+
+    /// Index of the first entry in view.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.state.offset()
+    }
+
     #[inline]
     pub fn index(&self) -> usize {
         self.index
@@ -278,26 +328,242 @@ fn quit_listener(event: &Event) -> bool {
 }
 
 fn normal_key_event(event: Event, res: &mut Resource) {
+    // Digits typed before a motion repeat it, like `20j`.
+    if let Event::Key(KeyEvent {
+        code: KeyCode::Char(c @ '0'..='9'),
+        modifiers: KeyModifiers::NONE,
+        ..
+    }) = event
+    {
+        if c != '0' || res.count().is_some() {
+            res.push_count(c as u32 - '0' as u32);
+            return;
+        }
+    }
+    let times = match event {
+        Event::Key(_) => res.take_count().unwrap_or(1),
+        _ => 1,
+    };
+
     match event {
+        Event::Mouse(mouse) => mouse_event(mouse, res),
+
         Event::Key(KeyEvent {
             code: KeyCode::Char('n'),
             modifiers: KeyModifiers::CONTROL,
             ..
         }) => {
+            res.entry_box_mut().set_prompt(Prompt::File);
+            res.pointer_mut().toggle();
+            res.entry_box_mut().toggle();
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('/'),
+            ..
+        }) => {
+            let curr_index = res.file_list_state().index();
+            let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) else {
+                return;
+            };
+            curr_buff.begin_search();
+            res.entry_box_mut()
+                .set_prompt(Prompt::Search(SearchMode::Regex));
+            res.pointer_mut().toggle();
+            res.entry_box_mut().toggle();
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('&'),
+            ..
+        }) => {
+            let curr_index = res.file_list_state().index();
+            let Some(curr_buff) = res.files().get_file_buff(curr_index) else {
+                return;
+            };
+            // Start from the current filter so it can be edited.
+            let (expr, mode) = match curr_buff.filter() {
+                Some(filter) => (filter.expr().to_string(), filter.mode()),
+                None => (String::new(), SearchMode::Regex),
+            };
+            res.entry_box_mut().set(&expr);
+            res.entry_box_mut().set_prompt(Prompt::Filter(mode));
+            res.pointer_mut().toggle();
+            res.entry_box_mut().toggle();
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('@'),
+            ..
+        }) => {
+            res.entry_box_mut().set_prompt(Prompt::Time);
             res.pointer_mut().toggle();
             res.entry_box_mut().toggle();
         }
 
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(':'),
+            ..
+        }) => {
+            res.entry_box_mut().set_prompt(Prompt::Goto);
+            res.pointer_mut().toggle();
+            res.entry_box_mut().toggle();
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('n'),
+            modifiers: KeyModifiers::NONE,
+            ..
+        }) => {
+            let curr_index = res.file_list_state().index();
+            let wrapped = res
+                .files_mut()
+                .get_file_buff_mut(curr_index)
+                .is_some_and(FileBuf::next_match);
+            if wrapped {
+                res.status_mut()
+                    .push(Notice::info("Search wrapped around to the top"));
+            }
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('N'),
+            ..
+        }) => {
+            let curr_index = res.file_list_state().index();
+            let wrapped = res
+                .files_mut()
+                .get_file_buff_mut(curr_index)
+                .is_some_and(FileBuf::prev_match);
+            if wrapped {
+                res.status_mut()
+                    .push(Notice::info("Search wrapped around to the bottom"));
+            }
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('r'),
+            modifiers: KeyModifiers::NONE,
+            ..
+        }) => {
+            let curr_index = res.file_list_state().index();
+            let Some(curr_buff) = res.files().get_file_buff(curr_index) else {
+                return;
+            };
+            let name = curr_buff.name().to_string();
+            let notice = match res.files_mut().reopen(curr_index) {
+                Ok(()) => Notice::info(format!("Reopened {name}")),
+                Err(err) => Notice::error(format!("Cannot reopen {name}: {err}")),
+            };
+            res.status_mut().push(notice);
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(' '),
+            ..
+        }) => {
+            let curr_index = res.file_list_state().index();
+            if let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) {
+                curr_buff.toggle_mark();
+            }
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('m'),
+            modifiers: KeyModifiers::NONE,
+            ..
+        }) => {
+            if let Err(err) = res.files_mut().merge() {
+                res.status_mut()
+                    .push(Notice::error(format!("Cannot merge files: {err}")));
+                return;
+            }
+            let len = res.files().len();
+            res.file_list_state_mut().set_size(len);
+            res.file_list_state_mut().bottom();
+        }
+
         Event::Key(KeyEvent {
             code: KeyCode::Char('d'),
             modifiers: KeyModifiers::CONTROL,
             ..
         }) => {
-            let id = match res.file_list_state_mut().close() {
-                Ok(id) => id,
-                Err(_) => return,
-            };
-            res.files_mut().close(id);
+            if res.pointer().cursor_at::<Files>() {
+                let id = match res.file_list_state_mut().close() {
+                    Ok(id) => id,
+                    Err(_) => return,
+                };
+                res.files_mut().close(id);
+            }
+            if res.pointer().cursor_at::<View>() {
+                let curr_index = res.file_list_state().index();
+                if let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) {
+                    curr_buff.down(half_page(curr_buff).saturating_mul(times));
+                }
+            }
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('u'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        }) => {
+            let curr_index = res.file_list_state().index();
+            if let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) {
+                curr_buff.up(half_page(curr_buff).saturating_mul(times));
+            }
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::PageDown,
+            ..
+        })
+        | Event::Key(KeyEvent {
+            code: KeyCode::Char('f'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        }) => {
+            let curr_index = res.file_list_state().index();
+            if let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) {
+                curr_buff.down(curr_buff.height().max(1).saturating_mul(times));
+            }
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::PageUp,
+            ..
+        })
+        | Event::Key(KeyEvent {
+            code: KeyCode::Char('b'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        }) => {
+            let curr_index = res.file_list_state().index();
+            if let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) {
+                curr_buff.up(curr_buff.height().max(1).saturating_mul(times));
+            }
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('t'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        }) => {
+            let curr_index = res.file_list_state().index();
+            if let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) {
+                curr_buff.toggle_tail();
+            }
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('a'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        }) => {
+            let curr_index = res.file_list_state().index();
+            if let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) {
+                curr_buff.toggle_colors();
+            }
         }
 
         Event::Key(KeyEvent {
@@ -318,12 +584,12 @@ fn normal_key_event(event: Event, res: &mut Resource) {
             ..
         }) => {
             if res.pointer().cursor_at::<Files>() {
-                res.file_list_state_mut().next();
+                res.file_list_state_mut().next(times);
             }
             if res.pointer().cursor_at::<View>() {
                 let curr_index = res.file_list_state().index();
                 if let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) {
-                    curr_buff.next();
+                    curr_buff.down(times);
                 }
             }
         }
@@ -334,12 +600,12 @@ fn normal_key_event(event: Event, res: &mut Resource) {
             ..
         }) => {
             if res.pointer().cursor_at::<Files>() {
-                res.file_list_state_mut().prev();
+                res.file_list_state_mut().prev(times);
             }
             if res.pointer().cursor_at::<View>() {
                 let curr_index = res.file_list_state().index();
                 if let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) {
-                    curr_buff.prev();
+                    curr_buff.up(times);
                 }
             }
         }
@@ -380,7 +646,93 @@ fn normal_key_event(event: Event, res: &mut Resource) {
     }
 }
 
+// The mouse is hit tested against where the panes were last drawn.
+fn mouse_event(mouse: MouseEvent, res: &mut Resource) {
+    let MouseEvent {
+        kind, column, row, ..
+    } = mouse;
+    let panes = res.panes();
+    let position = Position::new(column, row);
+    let curr_index = res.file_list_state().index();
+
+    match kind {
+        MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+            let down = kind == MouseEventKind::ScrollDown;
+            if panes.files.contains(position) {
+                match down {
+                    true => res.file_list_state_mut().next(1),
+                    false => res.file_list_state_mut().prev(1),
+                }
+            }
+            if panes.text.contains(position) {
+                if let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) {
+                    match down {
+                        true => curr_buff.down(WHEEL_LINES),
+                        false => curr_buff.up(WHEEL_LINES),
+                    }
+                }
+            }
+        }
+        MouseEventKind::Down(MouseButton::Left) => {
+            if panes.files.contains(position) {
+                res.pointer_mut().set_cursor::<Files>();
+            }
+            if panes.list.contains(position) {
+                let offset = res.file_list_state().offset();
+                let index = offset + usize::from(row - panes.list.top());
+                res.file_list_state_mut().select(index);
+            }
+            if panes.text.contains(position) {
+                res.pointer_mut().set_cursor::<View>();
+            }
+            if panes.on_scrollbar(column, row) {
+                res.dragging = true;
+                scroll_to(res, panes.scrollbar_ratio(row));
+            }
+        }
+        MouseEventKind::Drag(MouseButton::Left) if res.dragging => {
+            scroll_to(res, panes.scrollbar_ratio(row));
+        }
+        MouseEventKind::Up(MouseButton::Left) => res.dragging = false,
+        _ => {}
+    }
+}
+
+// Move the view of the current file this far through it, from zero to one.
+fn scroll_to(res: &mut Resource, ratio: f64) {
+    let curr_index = res.file_list_state().index();
+    if let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) {
+        let last = curr_buff.len().saturating_sub(curr_buff.height());
+        curr_buff.goto((last as f64 * ratio).round() as usize);
+    }
+}
+
+// Half of the lines that fit in the text pane, at least one.
+fn half_page(curr_buff: &FileBuf) -> usize {
+    (curr_buff.height() / 2).max(1)
+}
+
 fn write_key_event(event: Event, res: &mut Resource) -> anyhow::Result<()> {
+    match res.entry_box().prompt() {
+        Prompt::Search(mode) => {
+            search_key_event(event, mode, res);
+            return Ok(());
+        }
+        Prompt::Filter(mode) => {
+            filter_key_event(event, mode, res);
+            return Ok(());
+        }
+        Prompt::Time => {
+            apply_key_event(event, res, FileBuf::goto_time);
+            return Ok(());
+        }
+        Prompt::Goto => {
+            apply_key_event(event, res, FileBuf::goto_input);
+            return Ok(());
+        }
+        Prompt::File => {}
+    }
+
     match event {
         Event::Key(KeyEvent {
             code: KeyCode::Char('n'),
@@ -405,9 +757,19 @@ fn write_key_event(event: Event, res: &mut Resource) -> anyhow::Result<()> {
             code: KeyCode::Enter,
             ..
         }) => {
-            let name = res.entry_box().input_buff();
-            if let Err(err) = res.files_mut().insert(&name) {
-                log::trace!("Cannot open file due to: {err}");
+            let input = res.entry_box().input_buff();
+            let (watch, input) = split_watch_arg(&input);
+            let (lines, name) = split_lines_arg(input);
+            let mut options = res.files().options();
+            if lines.is_some() {
+                options.tail_lines = lines;
+            }
+            // `--watch` only applies to commands given on the command line.
+            options.watch = watch;
+
+            if let Err(err) = res.files_mut().insert(name, options) {
+                res.status_mut()
+                    .push(Notice::error(format!("Cannot open {name}: {err}")));
                 res.entry_box_mut().set_err();
                 return Ok(());
             }
@@ -440,3 +802,147 @@ fn write_key_event(event: Event, res: &mut Resource) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+// The search is updated on every key so matches show up while typing.
+fn search_key_event(event: Event, mode: SearchMode, res: &mut Resource) {
+    let curr_index = res.file_list_state().index();
+
+    match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('n'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        })
+        | Event::Key(KeyEvent {
+            code: KeyCode::Esc,
+            modifiers: KeyModifiers::NONE,
+            ..
+        }) => {
+            if let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) {
+                curr_buff.cancel_search();
+            }
+            close_entry_box(res);
+            return;
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::Enter,
+            ..
+        }) => {
+            if !res.entry_box().is_err() {
+                close_entry_box(res);
+            }
+            return;
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('r'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        }) => res
+            .entry_box_mut()
+            .set_prompt(Prompt::Search(mode.toggle())),
+        Event::Key(KeyEvent {
+            code: KeyCode::Backspace,
+            ..
+        }) => res.entry_box_mut().pop(),
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(c),
+            ..
+        }) => res.entry_box_mut().push(c),
+        _ => return,
+    }
+
+    let Prompt::Search(mode) = res.entry_box().prompt() else {
+        return;
+    };
+    let pattern = res.entry_box().input_buff();
+    let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) else {
+        return;
+    };
+    match curr_buff.set_search(&pattern, mode) {
+        Ok(()) => res.entry_box_mut().set_ok(),
+        Err(err) => {
+            res.status_mut()
+                .push(Notice::error(format!("Invalid search pattern: {err}")));
+            res.entry_box_mut().set_err();
+        }
+    }
+}
+
+// The filter is only applied on enter as it has to go through the whole file.
+fn filter_key_event(event: Event, mode: SearchMode, res: &mut Resource) {
+    if let Event::Key(KeyEvent {
+        code: KeyCode::Char('r'),
+        modifiers: KeyModifiers::CONTROL,
+        ..
+    }) = event
+    {
+        res.entry_box_mut()
+            .set_prompt(Prompt::Filter(mode.toggle()));
+        return;
+    }
+
+    apply_key_event(event, res, |curr_buff, expr| {
+        curr_buff.set_filter(expr, mode)
+    });
+}
+
+// For prompts applied to the current file on enter, the entry box stays open on errors.
+fn apply_key_event(
+    event: Event,
+    res: &mut Resource,
+    apply: impl FnOnce(&mut FileBuf, &str) -> anyhow::Result<()>,
+) {
+    match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('n'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        })
+        | Event::Key(KeyEvent {
+            code: KeyCode::Esc,
+            modifiers: KeyModifiers::NONE,
+            ..
+        }) => close_entry_box(res),
+        Event::Key(KeyEvent {
+            code: KeyCode::Enter,
+            ..
+        }) => {
+            let curr_index = res.file_list_state().index();
+            let input = res.entry_box().input_buff();
+            let Some(curr_buff) = res.files_mut().get_file_buff_mut(curr_index) else {
+                close_entry_box(res);
+                return;
+            };
+            match apply(curr_buff, &input) {
+                Ok(()) => close_entry_box(res),
+                Err(err) => {
+                    res.status_mut()
+                        .push(Notice::error(format!("Cannot use {input}: {err}")));
+                    res.entry_box_mut().set_err();
+                }
+            }
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::Backspace,
+            ..
+        }) => {
+            res.entry_box_mut().set_ok();
+            res.entry_box_mut().pop();
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(c),
+            ..
+        }) => {
+            res.entry_box_mut().set_ok();
+            res.entry_box_mut().push(c);
+        }
+        _ => {}
+    }
+}
+
+fn close_entry_box(res: &mut Resource) {
+    res.pointer_mut().toggle();
+    res.entry_box_mut().clear();
+    res.entry_box_mut().set_ok();
+    res.entry_box_mut().toggle();
+}
@@ -24,19 +24,89 @@
  * */
 
 use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::IsTerminal;
 use std::ops::{Deref, DerefMut};
-use std::path::Path;
+use std::path::{self, Path, PathBuf};
+use std::process::ExitStatus;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 use anyhow::anyhow;
+use chrono::Local;
 use ratatui::layout::*;
 
+use crate::index::LineIndex;
+use crate::merge::Merge;
+use crate::reader::{is_fifo, seek_last_lines, Chunk, Command, Source, Worker, INDEX_STRIDE};
+use crate::search::{Filter, Search, SearchMode};
+use crate::status::Notice;
+use crate::time::{self, TimeFormat, TimeParser};
+use crate::watch::FileWatcher;
+
+/// Files this large or larger are indexed rather than held in memory by default.
+pub const INDEX_THRESHOLD: u64 = 64 * 1024 * 1024;
+/// Lines shown when starting at the end of a file without a count.
+pub const TAIL_LINES: usize = 10;
+/// Shown above the first line when a file was opened past its start.
+pub const SKIPPED: &str = "──── earlier lines skipped ────";
+/// Path that stands for standard input.
+pub const STDIN: &str = "-";
+/// Starts a shell command to read the output of rather than a path.
+pub const COMMAND_PREFIX: char = '!';
+/// Shown in place of the content of a file that does not exist yet.
+pub const WAITING: &str = "──── waiting for file… ────";
+// Entries looked at by a search on each update.
+const SCAN_STEP: usize = 64 * 1024;
+// Entries looked at for a line or timestamp to go by before taking there to be none.
+const SEEK_SCAN: usize = 4096;
+// Wait before the first automatic attempt at reopening a file, doubled after each failure.
+const RETRY_MIN: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+// Shortest wait between two runs of a watched command.
+const WATCH_MIN: Duration = Duration::from_millis(100);
+
+/// Options applied to every file opened through the [`FileList`].
+#[derive(Clone)]
+pub struct BufOptions {
+    /// Files this large or larger are indexed instead of held in memory.
+    pub index_threshold: u64,
+    /// Only read the last n lines of the file, like `tail -n`.
+    pub tail_lines: Option<usize>,
+    /// Drop the oldest lines once more than this many are held.
+    pub max_lines: Option<usize>,
+    /// Drop the oldest lines once their text takes up more than this many bytes.
+    pub max_bytes: Option<usize>,
+    /// Tried before the common formats when detecting how timestamps are written.
+    pub time_format: Option<TimeFormat>,
+    /// Keep trying to reopen files that ran into an error, waiting longer after each attempt.
+    pub retry: bool,
+    /// Run commands again this often, replacing their output instead of appending to it.
+    pub watch: Option<Duration>,
+}
+
+impl Default for BufOptions {
+    fn default() -> Self {
+        BufOptions {
+            index_threshold: INDEX_THRESHOLD,
+            tail_lines: None,
+            max_lines: None,
+            max_bytes: None,
+            time_format: None,
+            retry: false,
+            watch: None,
+        }
+    }
+}
+
 /// A list to maintain names of the file. The actual file content will be saved
 /// into another object this type only to provide an ordered list of file names.
 #[derive(Default)]
 pub struct FileList {
     pub table: Vec<FileBuf>,
+    options: BufOptions,
+    watcher: FileWatcher,
 }
 
 impl Deref for FileList {
@@ -53,35 +123,157 @@ impl DerefMut for FileList {
 }
 
 impl FileList {
-    pub fn with_files(files: Vec<String>) -> anyhow::Result<Self> {
+    /// Files that cannot be opened are listed with the reason in place of their content,
+    /// files that do not exist yet are waited for.
+    pub fn with_files(files: Vec<String>, options: BufOptions) -> Self {
         let mut table = Vec::new();
-        for file in files.into_iter() {
-            table.push(FileBuf::new(&file, false)?);
+        let mut watcher = FileWatcher::new();
+        for name in files.into_iter() {
+            let file = match FileBuf::new(&name, false, options.clone()) {
+                Ok(file) => file,
+                Err(err) if is_not_found(&err) => FileBuf::waiting(&name, options.clone()),
+                Err(err) => FileBuf::failed(&name, options.clone(), err),
+            };
+            for path in file.watched() {
+                watcher.watch(path);
+            }
+            table.push(file);
+        }
+        FileList {
+            table,
+            options,
+            watcher,
         }
-        Ok(FileList { table })
     }
 
-    pub fn insert(&mut self, name: &str) -> anyhow::Result<()> {
-        let file = FileBuf::new(name, false)?;
+    /// Open another file. Adding a file that could not be opened before takes its place,
+    /// a file that does not exist yet is waited for.
+    pub fn insert(&mut self, name: &str, options: BufOptions) -> anyhow::Result<()> {
+        // Checked before opening so that a duplicate does not run its command or take stdin.
+        let path = open_path(name);
+        if self
+            .table
+            .iter()
+            .any(|f| !f.nulled && !f.waiting && f.path() == path)
+        {
+            return Err(anyhow!("File with path {} is already open", path.display()));
+        }
 
-        if self.table.iter().any(|f| f.path() == file.path()) {
-            return Err(anyhow!(
-                "File with path {} is already open",
-                file.path.display()
-            ));
+        let file = match FileBuf::new(name, false, options.clone()) {
+            Ok(file) => file,
+            Err(err) if is_not_found(&err) => FileBuf::waiting(name, options),
+            Err(err) => return Err(err),
+        };
+
+        if let Some(failed) = self.table.iter_mut().find(|f| {
+            (f.nulled || f.waiting)
+                && !file.origin().as_os_str().is_empty()
+                && f.origin() == file.origin()
+        }) {
+            swap(&mut self.watcher, failed, file);
+            return Ok(());
+        }
+
+        for path in file.watched() {
+            self.watcher.watch(path);
+        }
+        self.table.push(file);
+        Ok(())
+    }
+
+    /// Add a buffer merging the marked files by time, the marks are cleared.
+    pub fn merge(&mut self) -> anyhow::Result<()> {
+        let marked: Vec<&FileBuf> = self
+            .table
+            .iter()
+            .filter(|f| f.is_marked() && !f.nulled && !f.waiting && !f.stream)
+            .collect();
+        if marked.len() < 2 {
+            return Err(anyhow!("Mark at least two files to merge"));
         }
 
+        let mut files: Vec<(&str, &Path)> = Vec::new();
+        for (name, origin) in marked.iter().flat_map(|f| f.sources()) {
+            if !files.iter().any(|(_, path)| *path == origin) {
+                files.push((name, origin));
+            }
+        }
+        let file = FileBuf::merged(files, self.options.clone())?;
+
+        for path in file.watched() {
+            self.watcher.watch(path);
+        }
+        self.table
+            .iter_mut()
+            .filter(|f| f.is_marked())
+            .for_each(FileBuf::toggle_mark);
         self.table.push(file);
         Ok(())
     }
 
+    /// Read the file from the start again, going back to where it was left.
+    pub fn reopen(&mut self, id: usize) -> anyhow::Result<()> {
+        let Some(file) = self.table.get_mut(id) else {
+            return Ok(());
+        };
+        let reopened = file.reopen()?;
+        swap(&mut self.watcher, file, reopened);
+        Ok(())
+    }
+
     pub fn close(&mut self, id: usize) {
-        self.table.remove(id);
+        let file = self.table.remove(id);
+        for path in file.watched() {
+            self.watcher.unwatch(path);
+        }
+    }
+
+    /// Wake the readers of files that changed on disk and collect what they read.
+    /// Returns what happened to the files in the meantime.
+    pub fn update(&mut self) -> Vec<Notice> {
+        let changed = self.watcher.changed();
+        let mut notices = Vec::new();
+
+        for file in self.table.iter_mut() {
+            let watched = file.watched();
+            let touched = changed.iter().any(|path| watched.contains(&path.as_path()));
+            if touched && file.waiting && file.origin().exists() {
+                match file.reopen() {
+                    Ok(created) => {
+                        notices.push(Notice::info(format!("{} was created", file.name())));
+                        swap(&mut self.watcher, file, created);
+                    }
+                    Err(err) => log::trace!("Cannot open {} yet: {err}", file.name()),
+                }
+            }
+            if touched {
+                file.wake();
+            }
+            if let Err(err) = file.update() {
+                notices.push(Notice::error(format!("Cannot read {}: {err}", file.name())));
+                file.nullify(format!("{err}"));
+            }
+            if file.is_retry_due() {
+                match file.reopen() {
+                    Ok(reopened) => {
+                        let notice = match file.waiting {
+                            true => format!("{} was created", file.name()),
+                            false => format!("Reopened {}", file.name()),
+                        };
+                        notices.push(Notice::info(notice));
+                        swap(&mut self.watcher, file, reopened);
+                    }
+                    Err(err) => file.retry_later(err),
+                }
+            }
+            notices.append(&mut file.notices);
+        }
+        notices
     }
 
     #[inline]
-    pub fn names(&self) -> Vec<&str> {
-        self.iter().map(|f| f.name()).collect()
+    pub fn options(&self) -> BufOptions {
+        self.options.clone()
     }
 
     #[inline]
@@ -95,81 +287,1120 @@ impl FileList {
     }
 }
 
+// The path a file given by name is known by once opened, found without opening it.
+fn open_path(name: &str) -> PathBuf {
+    if let Some(command) = name.strip_prefix(COMMAND_PREFIX) {
+        return PathBuf::from(format!("{COMMAND_PREFIX}{}", command.trim()));
+    }
+    if name == STDIN {
+        return PathBuf::from(STDIN);
+    }
+    Path::new(name)
+        .canonicalize()
+        .unwrap_or_else(|_| path::absolute(name).unwrap_or_else(|_| Path::new(name).to_path_buf()))
+}
+
+// Whether opening a file failed because there is nothing at its path.
+fn is_not_found(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<std::io::Error>()
+        .is_some_and(|err| err.kind() == std::io::ErrorKind::NotFound)
+}
+
+// Put the reopened file in place of the old one, watching wherever it leads to now.
+fn swap(watcher: &mut FileWatcher, file: &mut FileBuf, reopened: FileBuf) {
+    for path in file.watched() {
+        watcher.unwatch(path);
+    }
+    for path in reopened.watched() {
+        watcher.watch(path);
+    }
+    *file = reopened;
+}
+
+/// A line to go to, as typed into the goto prompt.
+#[derive(Clone, Copy)]
+pub enum Goto {
+    /// The line with this number.
+    Line(usize),
+    /// This many lines after, or before, the line at the top of the view.
+    Offset(i64),
+    /// This far through the lines read so far.
+    Percent(f64),
+}
+
+impl FromStr for Goto {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> anyhow::Result<Self> {
+        let input = input.trim();
+        if let Some(percent) = input.strip_suffix('%') {
+            let percent: f64 = percent.trim().parse()?;
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(anyhow!("Percentage {percent} is out of range"));
+            }
+            return Ok(Goto::Percent(percent));
+        }
+        if input.starts_with(['+', '-']) {
+            return Ok(Goto::Offset(input.parse()?));
+        }
+        Ok(Goto::Line(input.parse()?))
+    }
+}
+
+// What the entries are in order of when looking one up.
+#[derive(Clone, Copy)]
+enum Order {
+    Line,
+    Time,
+}
+
+// Where a file was left when it was nullified, picked up again once it is reopened.
+#[derive(Clone, Default)]
+struct Resume {
+    // Line at the top of the view, unset when the view followed the end of the file.
+    line: Option<usize>,
+    is_tail: bool,
+    // Expression and mode of the filter, which is dropped along with the content.
+    filter: Option<(Box<str>, SearchMode)>,
+    // Next automatic attempt at reopening the file and the wait before the one after it.
+    retry: Option<(Instant, Duration)>,
+}
+
+/// A single line held by a [`FileBuf`]. Markers carry no line number and denote
+/// events such as rotation rather than file content.
+#[derive(Clone)]
+pub struct BufLine {
+    number: Option<usize>,
+    text: String,
+}
+
+impl BufLine {
+    pub fn content(number: usize, text: String) -> Self {
+        BufLine {
+            number: Some(number),
+            text,
+        }
+    }
+
+    pub fn marker(text: impl Into<String>) -> Self {
+        BufLine {
+            number: None,
+            text: text.into(),
+        }
+    }
+
+    #[inline]
+    pub fn number(&self) -> Option<usize> {
+        self.number
+    }
+
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    #[inline]
+    pub fn into_text(self) -> String {
+        self.text
+    }
+
+    #[inline]
+    pub fn is_marker(&self) -> bool {
+        self.number.is_none()
+    }
+}
+
 pub struct FileBuf {
     nulled: bool,
     is_tail: bool,
+    // Keep the view pinned to the bottom while tailing.
+    follow: bool,
+    // Still ingesting the file for the first time.
+    loading: bool,
+    // Render ANSI colors rather than stripping them.
+    colors: bool,
     name: Box<str>,
     path: Box<Path>,
-    reader: Option<BufReader<File>>,
+    // Path as given by the user, reopened by name when the file is rotated.
+    origin: Box<Path>,
+    // Picked in the file list to be merged.
+    marked: bool,
+    // Opened past the start of the file, so lines are numbered from where reading started.
+    relative: bool,
+    // The file does not exist yet and is read and tailed once it is created.
+    waiting: bool,
+    // Read from standard input, a named pipe or a command as it is written to.
+    stream: bool,
+    // Shell command the output is read from, run again when the buffer is reopened.
+    command: Option<Box<str>>,
+    exit_status: Option<ExitStatus>,
+    // Lines of a watched command that differ from its previous run, by line number.
+    changed: Vec<bool>,
+    worker: Option<Worker>,
+    // Set for the merged view of several files, read instead of the worker.
+    merge: Option<Merge>,
+    // Set for large files, which are then read from the disk rather than held in the buffer.
+    index: Option<LineIndex>,
+    filter: Option<Filter>,
+    // Lines in view when the file is indexed or filtered, and the view they were read for.
+    window: Vec<BufLine>,
+    window_view: Option<(usize, usize)>,
+    options: BufOptions,
+    // Lines evicted from the front of the buffer, noted in its first entry.
+    dropped: usize,
+    // Size of the text held by the buffer.
+    bytes: usize,
+    time: TimeParser,
+    search: Option<Search>,
+    // View position to search from and return to when the search is cancelled.
+    anchor: usize,
+    // Line to go to once it has been read.
+    pending: Option<usize>,
+    // Things that happened to the file since the status bar last asked.
+    notices: Vec<Notice>,
+    resume: Resume,
     view: RefCell<[usize; 2]>,
     view_update: bool,
     lines: usize,
-    buffer: Vec<String>,
+    buffer: VecDeque<BufLine>,
 }
 
 impl FileBuf {
-    pub fn new(path: &str, is_tail: bool) -> anyhow::Result<Self> {
-        let nulled = false;
+    pub fn new(path: &str, is_tail: bool, options: BufOptions) -> anyhow::Result<Self> {
+        if let Some(command) = path.strip_prefix(COMMAND_PREFIX) {
+            return FileBuf::command(command.trim(), options);
+        }
+        if path == STDIN {
+            if std::io::stdin().is_terminal() {
+                return Err(anyhow!(
+                    "Standard input is a terminal, pipe something into it"
+                ));
+            }
+            return FileBuf::stream(
+                "stdin",
+                Path::new(STDIN),
+                Path::new(""),
+                Source::Stdin,
+                options,
+            );
+        }
 
         let name = path.to_string().into_boxed_str();
+        let origin = path::absolute(path)?.into_boxed_path();
         let path = Path::new(path).canonicalize()?.into_boxed_path();
-        let file = File::open(path.as_ref())?;
-        let reader = Some(BufReader::new(file));
-        let buffer = Vec::new();
-        let view = RefCell::new(Default::default());
-        let view_update = true;
-        let lines = 1;
+        if is_fifo(&path.metadata()?) {
+            let fifo = Source::Fifo(path.to_path_buf());
+            return FileBuf::stream(&name, &path, &origin, fifo, options);
+        }
+        let mut file = File::open(path.as_ref())?;
+        let start = match options.tail_lines {
+            Some(lines) => seek_last_lines(&mut file, lines)?,
+            None => 0,
+        };
+        let index = match file.metadata()?.len() >= options.index_threshold {
+            true => Some(LineIndex::new(File::open(path.as_ref())?, start)),
+            false => None,
+        };
+        let worker = Worker::spawn(&origin, Source::File(file), is_tail, index.is_some())?;
 
         log::trace!("Opening a file with path {}", path.display());
 
-        Ok(FileBuf {
-            nulled,
-            is_tail,
+        let mut file = FileBuf::blank(name, path, origin, options);
+        if start > 0 && index.is_none() {
+            file.buffer.push_back(BufLine::marker(SKIPPED));
+        }
+        file.is_tail = is_tail;
+        file.follow = is_tail;
+        file.loading = true;
+        file.relative = start > 0;
+        file.worker = Some(worker);
+        file.index = index;
+        Ok(file)
+    }
+
+    // The output of a shell command, restarted by reopening it.
+    // Watched commands are run again every so often, each run replacing the last.
+    fn command(command: &str, options: BufOptions) -> anyhow::Result<Self> {
+        if command.is_empty() {
+            return Err(anyhow!("Type a command to run after {COMMAND_PREFIX}"));
+        }
+        log::trace!("Running the command {command}");
+
+        let name = format!("{COMMAND_PREFIX}{command}");
+        let watch = options.watch.map(|interval| interval.max(WATCH_MIN));
+        let source = match watch {
+            Some(interval) => Source::Watch(command.to_string(), interval),
+            None => Source::Command(command.to_string()),
+        };
+        let worker = Worker::spawn(Path::new(&name), source, watch.is_none(), false)?;
+        let mut file = FileBuf::blank(
+            name.as_str().into(),
+            Path::new(&name).into(),
+            Path::new("").into(),
+            options,
+        );
+        file.is_tail = watch.is_none();
+        file.follow = watch.is_none();
+        file.loading = true;
+        file.stream = true;
+        file.command = Some(command.into());
+        file.worker = Some(worker);
+        Ok(file)
+    }
+
+    // Standard input or a named pipe, read for as long as it is written to.
+    fn stream(
+        name: &str,
+        path: &Path,
+        origin: &Path,
+        source: Source,
+        options: BufOptions,
+    ) -> anyhow::Result<Self> {
+        log::trace!("Opening a stream {name}");
+
+        let worker = Worker::spawn(origin, source, true, false)?;
+        let mut file = FileBuf::blank(name.into(), path.into(), origin.into(), options);
+        file.is_tail = true;
+        file.follow = true;
+        file.loading = true;
+        file.stream = true;
+        file.worker = Some(worker);
+        Ok(file)
+    }
+
+    /// An entry for a file that could not be opened, showing why in place of its content.
+    pub fn failed(path: &str, options: BufOptions, err: anyhow::Error) -> Self {
+        let name = path.to_string().into_boxed_str();
+        let origin = path::absolute(path)
+            .unwrap_or_else(|_| Path::new(path).to_path_buf())
+            .into_boxed_path();
+        let mut file = FileBuf::blank(name, origin.clone(), origin, options);
+        file.notices
+            .push(Notice::error(format!("Cannot open {path}: {err}")));
+        file.nullify(format!("Cannot open {path}: {err}"));
+        file
+    }
+
+    /// An entry for a file that does not exist yet, tailed once it is created.
+    pub fn waiting(path: &str, options: BufOptions) -> Self {
+        let name = path.to_string().into_boxed_str();
+        let origin = path::absolute(path)
+            .unwrap_or_else(|_| Path::new(path).to_path_buf())
+            .into_boxed_path();
+        let mut file = FileBuf::blank(name, origin.clone(), origin, options);
+        file.buffer.push_back(BufLine::marker(WAITING));
+        file.waiting = true;
+        file.is_tail = true;
+        file.follow = true;
+        // Nothing is heard of files whose directory does not exist yet to be watched,
+        // so they are looked for every so often as well.
+        file.resume.retry = Some((Instant::now() + RETRY_MIN, RETRY_MIN));
+        file
+    }
+
+    /// Open the file again from its original path, going back to the line that was at the
+    /// top of the view and keeping its filter, marks and colors.
+    pub fn reopen(&mut self) -> anyhow::Result<Self> {
+        if self.origin.as_os_str().is_empty() && self.command.is_none() {
+            return Err(anyhow!(
+                "Only files, named pipes and commands can be reopened"
+            ));
+        }
+        let resume = match self.nulled {
+            true => self.resume.clone(),
+            false => self.resume_point()?,
+        };
+
+        let mut file = match self.command.as_ref() {
+            Some(command) => FileBuf::command(command, self.options.clone())?,
+            None => {
+                let origin = self.origin.to_string_lossy();
+                FileBuf::new(&origin, resume.is_tail, self.options.clone())?
+            }
+        };
+        file.name = self.name.clone();
+        file.colors = self.colors;
+        file.marked = self.marked;
+        if let Some((expr, mode)) = resume.filter {
+            file.set_filter(&expr, mode)?;
+        }
+        if let Some(line) = resume.line {
+            file.follow = false;
+            file.pending = Some(line);
+        }
+        Ok(file)
+    }
+
+    // The line at the top of the view and whether the file is tailed.
+    fn resume_point(&mut self) -> anyhow::Result<Resume> {
+        let top = self.view.borrow()[0];
+        let line = match self.follow {
+            true => None,
+            false => self
+                .next_key(top, self.len(), Order::Line)?
+                .map(|(_, line)| line as usize),
+        };
+        Ok(Resume {
+            line,
+            is_tail: self.is_tail,
+            filter: self.saved_filter(),
+            retry: None,
+        })
+    }
+
+    fn saved_filter(&self) -> Option<(Box<str>, SearchMode)> {
+        self.filter
+            .as_ref()
+            .map(|filter| (filter.expr().into(), filter.mode()))
+    }
+
+    // Whether the file ran into an error or does not exist yet and it is time to try
+    // opening it again.
+    fn is_retry_due(&self) -> bool {
+        (self.nulled || self.waiting)
+            && self
+                .resume
+                .retry
+                .is_some_and(|(at, _)| Instant::now() >= at)
+    }
+
+    // Wait twice as long as last time before trying to reopen the file again.
+    fn retry_later(&mut self, err: anyhow::Error) {
+        log::trace!("Cannot reopen {}: {err}", self.name);
+        if let Some((at, wait)) = self.resume.retry.as_mut() {
+            *wait = wait.saturating_mul(2).min(RETRY_MAX);
+            *at = Instant::now() + *wait;
+        }
+    }
+
+    /// Interleave the lines of several files by their timestamps, following them as they grow.
+    pub fn merged(files: Vec<(&str, &Path)>, options: BufOptions) -> anyhow::Result<Self> {
+        let names: Vec<&str> = files.iter().map(|(name, _)| *name).collect();
+        let name = format!("merged: {}", names.join(", ")).into_boxed_str();
+        let merge = Merge::new(files, &options)?;
+
+        let empty: Box<Path> = Path::new("").into();
+        let mut file = FileBuf::blank(name, empty.clone(), empty, options);
+        file.is_tail = true;
+        file.follow = true;
+        file.loading = true;
+        file.merge = Some(merge);
+        Ok(file)
+    }
+
+    // A buffer with nothing to read from yet.
+    fn blank(name: Box<str>, path: Box<Path>, origin: Box<Path>, options: BufOptions) -> Self {
+        let time = TimeParser::new(options.time_format.clone());
+
+        FileBuf {
+            nulled: false,
+            is_tail: false,
+            follow: false,
+            loading: false,
+            colors: true,
             name,
             path,
-            reader,
-            view,
-            lines,
-            view_update,
-            buffer,
-        })
+            origin,
+            marked: false,
+            relative: false,
+            waiting: false,
+            stream: false,
+            command: None,
+            exit_status: None,
+            changed: Vec::new(),
+            worker: None,
+            merge: None,
+            index: None,
+            filter: None,
+            window: Vec::new(),
+            window_view: None,
+            options,
+            dropped: 0,
+            bytes: 0,
+            time,
+            search: None,
+            anchor: 0,
+            pending: None,
+            notices: Vec::new(),
+            resume: Resume::default(),
+            view: RefCell::new(Default::default()),
+            view_update: true,
+            lines: 1,
+            buffer: VecDeque::new(),
+        }
     }
 
+    // Collect whatever the reader threads have sent since the last frame.
     pub fn update(&mut self) -> anyhow::Result<()> {
-        if self.is_tail && self.reader.is_none() {
-            let file = File::open(self.path())?;
-            self.reader = Some(BufReader::new(file));
+        match (self.worker.is_some(), self.merge.is_some()) {
+            (true, _) => self.read_chunks()?,
+            (_, true) => self.read_merged()?,
+            _ => return Ok(()),
+        }
+
+        self.evict();
+        self.scan_filter()?;
+        self.scan_search()?;
+        self.goto_pending()?;
+
+        if self.is_tail && self.follow {
+            self.bottom();
+        }
+
+        self.read_window()
+    }
+
+    fn read_chunks(&mut self) -> anyhow::Result<()> {
+        let Some(worker) = self.worker.as_ref() else {
+            return Ok(());
+        };
+
+        // Only the latest run of a watched command is shown.
+        let mut snapshot = None;
+        for chunk in worker.chunks() {
+            match chunk {
+                Chunk::Lines(lines) => {
+                    for line in lines {
+                        self.bytes += line.len();
+                        self.buffer.push_back(BufLine::content(self.lines, line));
+                        self.lines += 1;
+                    }
+                }
+                Chunk::Marker(marker) => {
+                    self.bytes += marker.len();
+                    self.buffer.push_back(BufLine::marker(marker));
+                }
+                Chunk::Index { lines, checkpoints } => {
+                    if let Some(index) = self.index.as_mut() {
+                        index.extend(lines, checkpoints);
+                    }
+                    self.lines += lines;
+                }
+                Chunk::Restart(file, marker) => {
+                    self.window_view = None;
+                    if let Some(index) = self.index.as_mut() {
+                        index.restart(file, marker);
+                    }
+                    if let Some(filter) = self.filter.as_mut() {
+                        filter.restart();
+                    }
+                    if let Some(search) = self.search.as_mut() {
+                        search.restart();
+                    }
+                }
+                Chunk::Loaded => self.loading = false,
+                Chunk::Event(event) => self.notices.push(event.notice(&self.name)),
+                Chunk::Exited(status) => {
+                    let notice = format!("{} finished with {status}", self.name);
+                    self.notices.push(match status.success() {
+                        true => Notice::info(notice),
+                        false => Notice::warn(notice),
+                    });
+                    let marker = format!("──── {status} ────");
+                    self.bytes += marker.len();
+                    self.buffer.push_back(BufLine::marker(marker));
+                    self.exit_status = Some(status);
+                }
+                Chunk::Snapshot(lines, status) => snapshot = Some((lines, status)),
+                Chunk::Error(err) => return Err(anyhow!(err)),
+            }
         }
+        if let Some((lines, status)) = snapshot {
+            self.window_view = None;
+            self.replace(lines, status);
+        }
+        Ok(())
+    }
+
+    // Swap in the output of the latest run of a watched command, noting the lines that
+    // differ from the run before. Nothing is noted on the first run.
+    fn replace(&mut self, lines: Vec<String>, status: ExitStatus) {
+        let first = self.exit_status.is_none();
+        let previous: Vec<&BufLine> = self.buffer.iter().filter(|l| !l.is_marker()).collect();
+        // Lines dropped from the front of the buffer are taken as unchanged.
+        let skipped = previous
+            .first()
+            .and_then(|line| line.number())
+            .map_or(0, |number| number - 1);
+        self.changed = lines
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let before = i.checked_sub(skipped).and_then(|i| previous.get(i));
+                !first && i >= skipped && before.is_none_or(|line| line.text() != text)
+            })
+            .collect();
 
-        let Some(reader) = self.reader.as_mut() else {
+        let marker = format!("──── {status} at {} ────", Local::now().format("%H:%M:%S"));
+        self.bytes = lines.iter().map(String::len).sum::<usize>() + marker.len();
+        self.lines = lines.len() + 1;
+        self.buffer = (1..)
+            .zip(lines)
+            .map(|(n, text)| BufLine::content(n, text))
+            .collect();
+        self.buffer.push_back(BufLine::marker(marker));
+        self.dropped = 0;
+        if let Some(filter) = self.filter.as_mut() {
+            filter.restart();
+        }
+        if let Some(search) = self.search.as_mut() {
+            search.restart();
+        }
+        self.exit_status = Some(status);
+        self.loading = false;
+
+        // Keep the view where it was unless the new output is shorter. The filter has yet
+        // to go through the new output, so only the unfiltered length is known.
+        let len = self.stored();
+        let mut view = self.view.borrow_mut();
+        let height = view[1].saturating_sub(view[0]);
+        view[0] = view[0].min(len.saturating_sub(height));
+        view[1] = view[0] + height;
+    }
+
+    fn read_merged(&mut self) -> anyhow::Result<()> {
+        let Some(merge) = self.merge.as_mut() else {
             return Ok(());
         };
 
-        let mut lines_to_read = self.view.borrow()[1];
-        let mut buffer = String::new();
+        merge.collect(&mut self.notices)?;
+        while let Some(line) = merge.pop() {
+            self.bytes += line.text().len();
+            self.lines += !line.is_marker() as usize;
+            self.buffer.push_back(line);
+        }
+        self.loading = !merge.is_loaded();
+        Ok(())
+    }
+
+    // Run the filter over the next few entries it has not seen yet.
+    fn scan_filter(&mut self) -> anyhow::Result<()> {
+        let len = self.stored();
+        let Some(filter) = self.filter.as_mut() else {
+            return Ok(());
+        };
+
+        let start = filter.scanned().min(len);
+        let end = len.min(start + SCAN_STEP);
+        if start == end {
+            return Ok(());
+        }
+        match self.index.as_mut() {
+            Some(index) => {
+                for line in index.read(start, end - start)? {
+                    filter.scan((!line.is_marker()).then_some(line.text()));
+                }
+            }
+            None => {
+                for line in self.buffer.range(start..end) {
+                    filter.scan((!line.is_marker()).then_some(line.text()));
+                }
+            }
+        }
 
-        while lines_to_read > 0 {
-            if reader.read_line(&mut buffer)? == 0 && !self.is_tail {
-                self.reader = None;
+        Ok(())
+    }
+
+    // Run the search over the next few entries it has not seen yet.
+    fn scan_search(&mut self) -> anyhow::Result<()> {
+        let len = self.len();
+        let Some(scanned) = self.search.as_ref().map(Search::scanned) else {
+            return Ok(());
+        };
+
+        let start = scanned.min(len);
+        let end = len.min(start + SCAN_STEP);
+        // Lines held in the buffer are looked at in place.
+        let read = match self.index.is_none() && self.filter.is_none() {
+            true => None,
+            false => Some(self.read_entries(start, end)?),
+        };
+        let lines: Vec<&BufLine> = match read.as_ref() {
+            Some(read) => read.iter().collect(),
+            None => self.buffer.range(start..end).collect(),
+        };
+        let settled = self.is_settled();
+        let Some(search) = self.search.as_mut() else {
+            return Ok(());
+        };
+
+        let mut jump = None;
+        for line in lines.iter() {
+            let pos = search.scan((!line.is_marker()).then_some(line.text()));
+            jump = jump.or(pos);
+        }
+        if search.scanned() >= len && settled {
+            jump = jump.or(search.scanned_all());
+        }
+
+        if let Some(pos) = jump {
+            self.goto(pos);
+        }
+
+        Ok(())
+    }
+
+    // Drop the oldest entries once the buffer is over its retention limits.
+    // Indexed files are read from the disk and never grow large in memory.
+    fn evict(&mut self) {
+        if self.index.is_some() {
+            return;
+        }
+
+        let BufOptions {
+            max_lines,
+            max_bytes,
+            ..
+        } = self.options;
+        let (max_lines, max_bytes) = (
+            max_lines.unwrap_or(usize::MAX),
+            max_bytes.unwrap_or(usize::MAX),
+        );
+
+        // The first entry is the note counting the dropped lines, if any.
+        let head = (self.dropped > 0) as usize;
+        let note = match head {
+            0 => None,
+            _ => self.buffer.pop_front(),
+        };
+        let mut evicted = 0;
+        let mut dropped = 0;
+        while self.buffer.len() > max_lines || self.bytes > max_bytes {
+            let Some(line) = self.buffer.pop_front() else {
                 break;
+            };
+            self.bytes = self.bytes.saturating_sub(line.text().len());
+            dropped += !line.is_marker() as usize;
+            evicted += 1;
+        }
+        if evicted == 0 {
+            if let Some(note) = note {
+                self.buffer.push_front(note);
             }
+            return;
+        }
+        self.dropped += dropped;
 
-            self.buffer.push(format!(
-                "{:>6}|  {}",
-                self.lines,
-                buffer
-                    .replace('\t', &"\u{000A0}".repeat(4))
-                    .replace('\r', "")
-            ));
+        let note = BufLine::marker(format!("──── {} earlier lines dropped ────", self.dropped));
+        self.buffer.push_front(note);
+
+        // Keep the same lines in view, or the oldest remaining ones if they were dropped.
+        // The note takes the place of an evicted entry unless there already was one.
+        let (end, removed) = (head + evicted, head + evicted - 1);
+        let (end, removed) = match self.filter.as_mut() {
+            Some(filter) => filter.shift(end, removed),
+            None => (end, removed),
+        };
+        self.window_view = None;
+        if let Some(search) = self.search.as_mut() {
+            search.shift(end, removed);
+        }
+        let mut view = self.view.borrow_mut();
+        let height = view[1].saturating_sub(view[0]);
+        view[0] = view[0].saturating_sub(removed);
+        view[1] = view[1].saturating_sub(removed).max(view[0] + height);
+    }
+
+    // Read the lines in view when they are not simply a slice of the buffer.
+    // Entries are only ever appended, so the ones still in view are kept and only those
+    // scrolled into it are read, which keeps the disk reads per frame to a view at most.
+    fn read_window(&mut self) -> anyhow::Result<()> {
+        if self.index.is_none() && self.filter.is_none() {
+            return Ok(());
+        }
+
+        let end = self.view.borrow()[1].min(self.len());
+        let start = self.view.borrow()[0].min(end);
+        let held = self
+            .window_view
+            .filter(|(first, last)| self.window.len() == last - first);
+        let Some((first, last)) = held else {
+            self.window = self.read_entries(start, end)?;
+            self.window_view = Some((start, end));
+            return Ok(());
+        };
+        if (first, last) == (start, end) {
+            return Ok(());
+        }
+
+        let (keep_start, keep_end) = (start.max(first), end.min(last));
+        if keep_start >= keep_end {
+            self.window = self.read_entries(start, end)?;
+            self.window_view = Some((start, end));
+            return Ok(());
+        }
+        let mut window = self.read_entries(start, keep_start)?;
+        window.extend(self.window.drain(keep_start - first..keep_end - first));
+        window.extend(self.read_entries(keep_end, end)?);
+        self.window = window;
+        self.window_view = Some((start, end));
+
+        Ok(())
+    }
+
+    // Read the entries from `start` to `end` as seen through the filter, if any.
+    fn read_entries(&mut self, start: usize, end: usize) -> anyhow::Result<Vec<BufLine>> {
+        let Some(filter) = self.filter.as_ref() else {
+            return match self.index.as_mut() {
+                Some(index) => index.read(start, end - start),
+                None => Ok(self.buffer.range(start..end).cloned().collect()),
+            };
+        };
+
+        let positions = &filter.positions()[start..end];
+        let Some(index) = self.index.as_mut() else {
+            return Ok(positions
+                .iter()
+                .map(|&pos| self.buffer[pos].clone())
+                .collect());
+        };
 
-            self.lines += 1;
-            lines_to_read -= 1;
-            buffer.clear();
+        // Kept lines close to each other are read from the disk together.
+        let mut lines = Vec::with_capacity(positions.len());
+        let mut rest = positions;
+        while let Some(&first) = rest.first() {
+            let run = 1 + rest
+                .windows(2)
+                .take_while(|pair| pair[1] - pair[0] <= INDEX_STRIDE)
+                .count();
+            let last = rest[run - 1];
+            let read = index.read(first, last - first + 1)?;
+            lines.extend(
+                rest[..run]
+                    .iter()
+                    .filter_map(|&pos| read.get(pos - first).cloned()),
+            );
+            rest = &rest[run..];
         }
+        Ok(lines)
+    }
+
+    // Number of entries held in the buffer or the index, regardless of the filter.
+    #[inline]
+    fn stored(&self) -> usize {
+        match self.index.as_ref() {
+            Some(index) => index.len(),
+            None => self.buffer.len(),
+        }
+    }
+
+    // Whether the file and the filter have been gone through entirely.
+    fn is_settled(&self) -> bool {
+        let filtered = self
+            .filter
+            .as_ref()
+            .is_none_or(|filter| filter.scanned() >= self.stored());
+        !self.loading && filtered
+    }
+
+    /// Number of entries that can be scrolled through, including markers.
+    #[inline]
+    pub fn len(&self) -> usize {
+        match self.filter.as_ref() {
+            Some(filter) => filter.positions().len(),
+            None => self.stored(),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn wake(&self) {
+        self.send(Command::Wake);
+    }
+
+    pub fn toggle_tail(&mut self) {
+        // Streams are read as they are written to and cannot stop being followed.
+        if self.stream || (self.worker.is_none() && self.merge.is_none()) {
+            return;
+        }
+        self.is_tail = !self.is_tail;
+        self.follow = self.is_tail;
+        self.send(Command::Tail(self.is_tail));
+    }
+
+    fn send(&self, command: Command) {
+        if let Some(worker) = self.worker.as_ref() {
+            worker.send(command);
+        }
+        if let Some(merge) = self.merge.as_ref() {
+            merge.send(command);
+        }
+    }
+
+    #[inline]
+    pub fn is_tail(&self) -> bool {
+        self.is_tail
+    }
+
+    pub fn toggle_colors(&mut self) {
+        self.colors = !self.colors;
+    }
+
+    #[inline]
+    pub fn colors(&self) -> bool {
+        self.colors
+    }
+
+    /// Remember the view position before searching incrementally.
+    pub fn begin_search(&mut self) {
+        self.anchor = self.view.borrow()[0];
+    }
+
+    /// Search for `pattern` from where the search began, an empty one clears the search.
+    pub fn set_search(&mut self, pattern: &str, mode: SearchMode) -> anyhow::Result<()> {
+        self.goto(self.anchor);
+        self.search = None;
+        if !pattern.is_empty() {
+            self.search = Some(Search::new(pattern, mode, self.anchor)?);
+            self.scan_search()?;
+        }
+        Ok(())
+    }
+
+    pub fn cancel_search(&mut self) {
+        self.search = None;
+        self.goto(self.anchor);
+    }
+
+    #[inline]
+    pub fn search(&self) -> Option<&Search> {
+        self.search.as_ref()
+    }
+
+    /// The current match, the number of matches and whether the search covered everything.
+    pub fn search_status(&self) -> Option<(Option<usize>, usize, bool)> {
+        let search = self.search.as_ref()?;
+        let (current, total) = search.position();
+        let done = search.scanned() >= self.len() && self.is_settled();
+        Some((current, total, done))
+    }
+
+    /// Jump to the next match, returns whether the search wrapped around.
+    pub fn next_match(&mut self) -> bool {
+        let start = self.view.borrow()[0];
+        let Some((pos, wrapped)) = self.search.as_mut().and_then(|search| search.next(start))
+        else {
+            return false;
+        };
+        self.goto(pos);
+        wrapped
+    }
+
+    /// Jump to the previous match, returns whether the search wrapped around.
+    pub fn prev_match(&mut self) -> bool {
+        let start = self.view.borrow()[0];
+        let Some((pos, wrapped)) = self.search.as_mut().and_then(|search| search.prev(start))
+        else {
+            return false;
+        };
+        self.goto(pos);
+        wrapped
+    }
+
+    /// Only show the lines that pass `expr`, an empty one clears the filter.
+    pub fn set_filter(&mut self, expr: &str, mode: SearchMode) -> anyhow::Result<()> {
+        self.filter = match expr.is_empty() {
+            true => None,
+            false => Some(Filter::new(expr, mode)?),
+        };
+        self.window_view = None;
+
+        // Matches are positions in what is seen through the filter.
+        if let Some(search) = self.search.as_mut() {
+            search.restart();
+        }
+        {
+            let mut view = self.view.borrow_mut();
+            let height = view[1].saturating_sub(view[0]);
+            *view = [0, height];
+        }
+
+        self.scan_filter()?;
+        if self.follow {
+            self.bottom();
+        }
+        self.read_window()
+    }
+
+    #[inline]
+    pub fn filter(&self) -> Option<&Filter> {
+        self.filter.as_ref()
+    }
+
+    // The first entry from `start` up to `end` with a line number or timestamp,
+    // and its value.
+    fn next_key(
+        &mut self,
+        start: usize,
+        end: usize,
+        order: Order,
+    ) -> anyhow::Result<Option<(usize, i64)>> {
+        let end = end.min(self.len()).min(start.saturating_add(SEEK_SCAN));
+        let mut pos = start;
+        while pos < end {
+            let step = end.min(pos + INDEX_STRIDE);
+            for line in self.read_entries(pos, step)? {
+                let key = match (line.number(), order) {
+                    (None, _) => None,
+                    (Some(number), Order::Line) => Some(number as i64),
+                    (Some(_), Order::Time) => self.time.parse(line.text()),
+                };
+                if let Some(key) = key {
+                    return Ok(Some((pos, key)));
+                }
+                pos += 1;
+            }
+        }
+        Ok(None)
+    }
+
+    // The first entry with a line number or timestamp at or after `target`.
+    // Entries without one are skipped over, they are otherwise taken to be in order.
+    fn seek(&mut self, target: i64, order: Order) -> anyhow::Result<usize> {
+        let len = self.len();
+        let (mut low, mut high) = (0, len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.next_key(mid, high, order)? {
+                Some((pos, key)) if key < target => low = pos + 1,
+                _ => high = mid,
+            }
+        }
+        Ok(match self.next_key(low, len, order)? {
+            Some((pos, _)) => pos,
+            None => len,
+        })
+    }
+
+    /// Move the view to the first line at or after the time typed in by the user.
+    /// A time of day alone is taken on the day of the lines in view, in their offset.
+    pub fn goto_time(&mut self, input: &str) -> anyhow::Result<()> {
+        let len = self.len();
+        let top = self.view.borrow()[0];
+        let day = self
+            .next_key(top, len, Order::Time)?
+            .or(self.next_key(0, len, Order::Time)?);
+        let Some((pos, day)) = day else {
+            return Err(anyhow!("No timestamps found in {}", self.name));
+        };
+        // Typed times are read in the offset the timestamp found is written in.
+        let offset = self
+            .read_entries(pos, pos + 1)?
+            .first()
+            .and_then(|line| self.time.stamp(line.text()))
+            .map(|(_, offset)| offset)
+            .ok_or_else(|| anyhow!("No timestamps found in {}", self.name))?;
+        let target =
+            time::parse_input(input, day, offset).ok_or_else(|| anyhow!("Invalid time {input}"))?;
 
+        let pos = self.seek(target, Order::Time)?;
+        self.goto(pos);
         Ok(())
     }
 
+    /// Move the view to a line typed into the goto prompt. Lines that have not
+    /// been read yet are gone to once they are.
+    pub fn goto_input(&mut self, input: &str) -> anyhow::Result<()> {
+        let line = match input.parse()? {
+            Goto::Line(_) if self.relative => {
+                return Err(anyhow!(
+                    "{} was opened near its end and is numbered from there, \
+                     use an offset or a percentage",
+                    self.name
+                ));
+            }
+            Goto::Line(line) => line,
+            Goto::Offset(offset) => {
+                let top = self.view.borrow()[0];
+                let (_, number) = self
+                    .next_key(top, self.len(), Order::Line)?
+                    .unwrap_or((top, self.lines as i64));
+                number.saturating_add(offset).max(1) as usize
+            }
+            Goto::Percent(percent) => {
+                ((self.line_count() as f64 * percent / 100.0).ceil() as usize).max(1)
+            }
+        };
+
+        self.follow = false;
+        self.pending = Some(line);
+        self.goto_pending()
+    }
+
+    // Go to the pending line once it was read, or to the end if it never will be.
+    fn goto_pending(&mut self) -> anyhow::Result<()> {
+        let Some(line) = self.pending else {
+            return Ok(());
+        };
+        let unread = line >= self.lines && self.loading;
+        let unfiltered = self
+            .filter
+            .as_ref()
+            .is_some_and(|filter| filter.scanned() < self.stored());
+        if unread || unfiltered {
+            return Ok(());
+        }
+
+        self.pending = None;
+        let pos = self.seek(line as i64, Order::Line)?;
+        self.goto(pos);
+        Ok(())
+    }
+
+    /// The line to go to once it has been read.
+    #[inline]
+    pub fn pending_line(&self) -> Option<usize> {
+        self.pending
+    }
+
+    /// How the command read from finished, once it has.
+    #[inline]
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.exit_status
+    }
+
+    /// How often the command read from is run again, if it is watched.
+    #[inline]
+    pub fn watch_interval(&self) -> Option<Duration> {
+        let interval = self.command.as_ref().and(self.options.watch)?;
+        Some(interval.max(WATCH_MIN))
+    }
+
+    /// The line differs from the one in its place on the previous run of a watched command.
+    #[inline]
+    pub fn is_changed(&self, number: usize) -> bool {
+        number
+            .checked_sub(1)
+            .and_then(|i| self.changed.get(i))
+            .is_some_and(|changed| *changed)
+    }
+
+    /// Lines are numbered from where reading started rather than from the start of the file.
+    #[inline]
+    pub fn is_relative(&self) -> bool {
+        self.relative
+    }
+
+    /// The file does not exist yet.
+    #[inline]
+    pub fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+
+    #[inline]
+    pub fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    /// Number of lines read from the file so far.
+    #[inline]
+    pub fn line_count(&self) -> usize {
+        self.lines.saturating_sub(1)
+    }
+
     pub fn detrigger_view_update(&mut self) {
         if self.view_update {
             self.view_update = false;
@@ -184,61 +1415,114 @@ impl FileBuf {
     }
 
     // Only return lines that are visible on the screen.
-    pub fn buffer<'a>(&'a self, rect: Rect) -> (Vec<&'a str>, bool) {
+    pub fn buffer(&self, rect: Rect) -> (Vec<&BufLine>, bool) {
         if self.view_update {
+            // The pane may be too small to hold even the borders.
+            let height = usize::from(rect.as_size().height).saturating_sub(2);
             let mut view = self.view.borrow_mut();
-            view[1] = view[0]
-                .saturating_add(rect.as_size().height.into())
-                .saturating_sub(2);
+            // A buffer that was moved to its end before it was first drawn has no height
+            // yet, so it would start past the end once it is given one.
+            view[0] = view[0].min(self.len().saturating_sub(height));
+            view[1] = view[0].saturating_add(height);
+        }
+
+        if self.index.is_some() || self.filter.is_some() {
+            return (self.window.iter().collect(), self.nulled);
         }
 
         let len = self.buffer.len();
         let (start, end) = (self.view.borrow()[0], self.view.borrow()[1]);
-        let lines = self
-            .buffer
-            .iter()
-            .map(String::as_str)
-            .collect::<Vec<&'a str>>();
-
-        let slice = if len >= end && start < end {
-            &lines[start..end]
+        let lines = if len >= end && start < end {
+            self.buffer.range(start..end).collect()
         } else {
-            &lines[..]
+            self.buffer.iter().collect()
         };
 
-        (slice.to_vec(), self.nulled)
+        (lines, self.nulled)
     }
 
     // Replace the buffer with the error message and close the file reader.
     pub fn nullify(&mut self, message: String) {
+        if !self.nulled {
+            self.resume = self.resume_point().unwrap_or_else(|_| Resume {
+                filter: self.saved_filter(),
+                ..Default::default()
+            });
+        }
+        let retry = self.options.retry && !self.origin.as_os_str().is_empty();
+        self.resume.retry = retry.then(|| (Instant::now() + RETRY_MIN, RETRY_MIN));
         self.nulled = true;
-        self.buffer = vec![message];
-        let _ = self.reader.take();
+        self.buffer = VecDeque::from([BufLine::marker(message)]);
+        self.dropped = 0;
+        self.bytes = 0;
+        self.search = None;
+        self.filter = None;
+        self.pending = None;
+        let _ = self.worker.take();
+        let _ = self.merge.take();
+        let _ = self.index.take();
         self.view = RefCell::new([0, 1]);
         self.is_tail = false;
+        self.follow = false;
+        self.loading = false;
     }
 
-    pub fn next(&mut self) {
-        let len = self.buffer.len();
+    /// Scroll down by up to `lines` lines.
+    pub fn down(&mut self, lines: usize) {
+        let len = self.len();
         let (start, end) = (self.view.borrow()[0], self.view.borrow()[1]);
-        if end < len {
+        let lines = lines.min(len.saturating_sub(end));
+        if lines > 0 {
             let mut view = self.view.borrow_mut();
-            view[0] = start.saturating_add(1);
-            view[1] = end.saturating_add(1);
+            view[0] = start.saturating_add(lines);
+            view[1] = end.saturating_add(lines);
         }
+        // Scrolling back down to the end resumes following.
+        self.follow = self.is_tail && self.view.borrow()[1] >= len;
     }
 
-    pub fn prev(&mut self) {
+    /// Scroll up by up to `lines` lines.
+    pub fn up(&mut self, lines: usize) {
         let (start, end) = (self.view.borrow()[0], self.view.borrow()[1]);
-        if start > 0 {
+        let lines = lines.min(start);
+        self.follow = false;
+        if lines > 0 {
             let mut view = self.view.borrow_mut();
-            view[0] = start.saturating_sub(1);
-            view[1] = end.saturating_sub(1);
+            view[0] = start.saturating_sub(lines);
+            view[1] = end.saturating_sub(lines);
         }
     }
 
+    /// Number of lines that fit in the text pane.
+    #[inline]
+    pub fn height(&self) -> usize {
+        let view = self.view.borrow();
+        view[1].saturating_sub(view[0])
+    }
+
+    /// Index of the first entry in the text pane.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.view.borrow()[0]
+    }
+
+    /// The first and last entries in view counting from one, and the number of entries.
+    /// Without a filter the lines dropped from the front of the buffer are counted too.
+    pub fn view_range(&self) -> (usize, usize, usize) {
+        let len = self.len();
+        let end = self.view.borrow()[1].min(len);
+        let start = self.view.borrow()[0].min(end);
+        // The note counting the dropped lines takes the place of one of them.
+        let dropped = match self.filter {
+            Some(_) => 0,
+            None => self.dropped.saturating_sub(1),
+        };
+        (start + 1 + dropped, end + dropped, len + dropped)
+    }
+
     pub fn top(&mut self) {
         let (start, end) = (self.view.borrow()[0], self.view.borrow()[1]);
+        self.follow = false;
         if start > 0 {
             let mut view = self.view.borrow_mut();
             view[0] = 0;
@@ -246,8 +1530,9 @@ impl FileBuf {
         }
     }
     pub fn bottom(&mut self) {
-        let len = self.buffer.len();
+        let len = self.len();
         let (start, end) = (self.view.borrow()[0], self.view.borrow()[1]);
+        self.follow = self.is_tail;
         if end < len {
             let diff = len.saturating_sub(end);
             let mut view = self.view.borrow_mut();
@@ -256,13 +1541,167 @@ impl FileBuf {
         }
     }
 
+    /// Move the view to start at `pos`, or to the bottom when that is closer.
+    pub fn goto(&mut self, pos: usize) {
+        let len = self.len();
+        let mut view = self.view.borrow_mut();
+        let height = view[1].saturating_sub(view[0]);
+        view[0] = pos.min(len.saturating_sub(height));
+        view[1] = view[0] + height;
+        self.follow = self.is_tail && view[1] >= len;
+    }
+
     #[inline]
     pub fn path(&self) -> &Path {
         &self.path
     }
 
+    #[inline]
+    pub fn origin(&self) -> &Path {
+        &self.origin
+    }
+
     #[inline]
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// The name shown in the file list, with the filter if one is set.
+    pub fn label(&self) -> String {
+        let mark = if self.marked { "● " } else { "" };
+        match self.filter.as_ref() {
+            Some(filter) => format!("{mark}{} [{}]", self.name, filter.expr()),
+            None => format!("{mark}{}", self.name),
+        }
+    }
+
+    /// Names and paths of the files read into this buffer.
+    /// Paths whose changes on disk concern the file: the paths it was opened by and,
+    /// when that is a symlink, the file it leads to.
+    pub fn watched(&self) -> Vec<&Path> {
+        let mut paths: Vec<&Path> = self.sources().into_iter().map(|(_, p)| p).collect();
+        let linked = !self.origin.as_os_str().is_empty() && self.path != self.origin;
+        if self.merge.is_none() && linked {
+            paths.push(&self.path);
+        }
+        paths
+    }
+
+    pub fn sources(&self) -> Vec<(&str, &Path)> {
+        match self.merge.as_ref() {
+            Some(merge) => merge.sources().collect(),
+            None => vec![(self.name(), self.origin())],
+        }
+    }
+
+    pub fn toggle_mark(&mut self) {
+        self.marked = !self.marked;
+    }
+
+    #[inline]
+    pub fn is_marked(&self) -> bool {
+        self.marked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A buffer holding `texts` as lines numbered from one, keeping at most `max_lines`.
+    fn buffered(texts: &[&str], max_lines: usize) -> FileBuf {
+        let options = BufOptions {
+            max_lines: Some(max_lines),
+            ..Default::default()
+        };
+        let path: Box<Path> = Path::new("test").into();
+        let mut file = FileBuf::blank("test".into(), path.clone(), path, options);
+        push(&mut file, texts);
+        file
+    }
+
+    fn push(file: &mut FileBuf, texts: &[&str]) {
+        for text in texts {
+            let last = file.buffer.iter().rev().find_map(BufLine::number);
+            let number = last.unwrap_or(file.dropped) + 1;
+            file.bytes += text.len();
+            file.buffer
+                .push_back(BufLine::content(number, text.to_string()));
+        }
+    }
+
+    fn texts(file: &FileBuf) -> Vec<&str> {
+        file.buffer.iter().map(BufLine::text).collect()
+    }
+
+    #[test]
+    fn evict_keeps_the_lines_in_view() {
+        let mut file = buffered(&["1", "2", "3", "4", "5", "6", "7", "8"], 5);
+        *file.view.borrow_mut() = [4, 6];
+
+        file.evict();
+        assert_eq!(
+            texts(&file),
+            ["──── 3 earlier lines dropped ────", "4", "5", "6", "7", "8"]
+        );
+        assert_eq!(file.dropped, 3);
+        assert_eq!(*file.view.borrow(), [2, 4]);
+
+        // Nothing more to drop, the note stays as it is.
+        file.evict();
+        assert_eq!(texts(&file).len(), 6);
+        assert_eq!(*file.view.borrow(), [2, 4]);
+    }
+
+    #[test]
+    fn evict_replaces_the_note() {
+        let mut file = buffered(&["1", "2", "3", "4", "5", "6", "7", "8"], 5);
+        file.evict();
+        push(&mut file, &["9", "10"]);
+        *file.view.borrow_mut() = [2, 4];
+
+        file.evict();
+        assert_eq!(
+            texts(&file),
+            [
+                "──── 5 earlier lines dropped ────",
+                "6",
+                "7",
+                "8",
+                "9",
+                "10"
+            ]
+        );
+        assert_eq!(file.buffer[1].number(), Some(6));
+        // The lines in view were dropped, the oldest remaining ones take their place.
+        assert_eq!(*file.view.borrow(), [0, 2]);
+    }
+
+    #[test]
+    fn evict_shifts_the_filter_and_search() {
+        let mut file = buffered(&["x1", "y2", "x3", "y4", "x5"], 3);
+        let mut filter = Filter::new("x", SearchMode::Literal).unwrap();
+        let mut search = Search::new("5", SearchMode::Literal, usize::MAX).unwrap();
+        for line in file.buffer.iter() {
+            filter.scan(Some(line.text()));
+        }
+        // The search goes through the entries kept by the filter.
+        for &pos in filter.positions() {
+            search.scan(Some(file.buffer[pos].text()));
+        }
+        file.filter = Some(filter);
+        file.search = Some(search);
+        // Through the filter, in view is x3.
+        *file.view.borrow_mut() = [1, 2];
+
+        file.evict();
+        assert_eq!(
+            texts(&file),
+            ["──── 2 earlier lines dropped ────", "x3", "y4", "x5"]
+        );
+        let filter = file.filter.as_ref().unwrap();
+        assert_eq!(filter.positions(), [0, 1, 3]);
+        assert_eq!(*file.view.borrow(), [1, 2]);
+        assert_eq!(file.search.as_mut().unwrap().next(0), Some((2, false)));
+    }
 }
@@ -24,11 +24,18 @@
  * */
 
 pub mod cursor;
+mod index;
 pub mod input;
 pub mod io;
+mod merge;
+mod reader;
 pub mod resource;
+mod search;
+mod status;
+mod time;
 mod ui;
 mod utils;
+mod watch;
 
 use std::{
     fs::{create_dir_all, OpenOptions},
@@ -130,7 +137,7 @@ fn main() -> anyhow::Result<()> {
                 Some(s) => *s,
                 None => match info.payload().downcast_ref::<String>() {
                     Some(s) => &s[..],
-                    None => &default_type,
+                    None => default_type,
                 },
             };
             panic_buff.push_str(msg);
@@ -186,7 +193,6 @@ fn create_logger() -> anyhow::Result<()> {
     let log_path = log_dir.join(LOGFILE_NAME);
 
     let log_file = OpenOptions::new()
-        .write(true)
         .append(true)
         .create(true)
         .open(log_path)
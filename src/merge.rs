@@ -0,0 +1,185 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2024 Mohammed Rehaan and contributors
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ * */
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::anyhow;
+
+use crate::io::{BufLine, BufOptions};
+use crate::reader::{self, seek_last_lines, Chunk, Command, Worker};
+use crate::status::Notice;
+use crate::time::TimeParser;
+
+// Colors the sources are told apart by, as SGR codes.
+const COLORS: [u8; 6] = [36, 35, 32, 33, 34, 31];
+
+/// Several files read side by side and interleaved by the timestamps on their lines.
+///
+/// Lines are released in order once every source has either read up to them or
+/// caught up with its file, so the merged lines only ever get appended. Lines
+/// without a timestamp, like the rest of a stack trace, stay after the line before.
+///
+/// Merged lines are numbered in the order they are released, the line number in their
+/// own file is part of the tag. The files are held in memory rather than indexed,
+/// however large they are.
+pub struct Merge {
+    sources: Vec<Source>,
+    // Number of the next merged line.
+    lines: usize,
+}
+
+struct Source {
+    name: Box<str>,
+    origin: Box<Path>,
+    // Put in front of every line along with its line number, so its source can be told.
+    tag: String,
+    worker: Worker,
+    queue: VecDeque<(i64, BufLine)>,
+    parser: TimeParser,
+    lines: usize,
+    // Timestamp of the last line that had one.
+    time: i64,
+    loaded: bool,
+}
+
+impl Merge {
+    /// Follow the files by their name and path, starting at their last
+    /// `tail_lines` lines if given.
+    pub fn new(files: Vec<(&str, &Path)>, options: &BufOptions) -> anyhow::Result<Self> {
+        let width = files
+            .iter()
+            .map(|(name, _)| short_name(name).chars().count())
+            .max()
+            .unwrap_or_default();
+
+        let mut sources = Vec::with_capacity(files.len());
+        for (id, (name, origin)) in files.into_iter().enumerate() {
+            let mut file = File::open(origin)?;
+            if let Some(lines) = options.tail_lines {
+                seek_last_lines(&mut file, lines)?;
+            }
+            let color = COLORS[id % COLORS.len()];
+            sources.push(Source {
+                name: name.into(),
+                origin: origin.into(),
+                tag: format!("\x1b[{color}m{:<width$}\x1b[0m", short_name(name)),
+                worker: Worker::spawn(origin, reader::Source::File(file), true, false)?,
+                queue: VecDeque::new(),
+                parser: TimeParser::new(options.time_format.clone()),
+                lines: 1,
+                time: i64::MIN,
+                loaded: false,
+            });
+        }
+
+        Ok(Merge { sources, lines: 1 })
+    }
+
+    /// Names and paths of the merged files.
+    pub fn sources(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.sources
+            .iter()
+            .map(|source| (source.name.as_ref(), source.origin.as_ref()))
+    }
+
+    pub fn send(&self, command: Command) {
+        for source in self.sources.iter() {
+            source.worker.send(command);
+        }
+    }
+
+    /// Queue up whatever the readers have sent since the last frame.
+    pub fn collect(&mut self, notices: &mut Vec<Notice>) -> anyhow::Result<()> {
+        for source in self.sources.iter_mut() {
+            for chunk in source.worker.chunks() {
+                match chunk {
+                    Chunk::Lines(lines) => {
+                        for line in lines {
+                            source.time = source.parser.parse(&line).unwrap_or(source.time);
+                            let text = format!("{} {:>6} │ {line}", source.tag, source.lines);
+                            let line = BufLine::content(source.lines, text);
+                            source.queue.push_back((source.time, line));
+                            source.lines += 1;
+                        }
+                    }
+                    Chunk::Marker(marker) => {
+                        let text = format!("{} {:>6} │ {marker}", source.tag, "");
+                        source.queue.push_back((source.time, BufLine::marker(text)));
+                    }
+                    Chunk::Loaded => source.loaded = true,
+                    Chunk::Event(event) => {
+                        notices.push(event.notice(&source.name));
+                    }
+                    Chunk::Error(err) => return Err(anyhow!("{}: {err}", source.name)),
+                    Chunk::Index { .. }
+                    | Chunk::Restart(..)
+                    | Chunk::Exited(_)
+                    | Chunk::Snapshot(..) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The next line in order of time, if it can be told yet.
+    pub fn pop(&mut self) -> Option<BufLine> {
+        // A source still reading its file may yet have older lines.
+        if self
+            .sources
+            .iter()
+            .any(|source| !source.loaded && source.queue.is_empty())
+        {
+            return None;
+        }
+
+        let source = self
+            .sources
+            .iter_mut()
+            .filter(|source| !source.queue.is_empty())
+            .min_by_key(|source| source.queue[0].0)?;
+        let (_, line) = source.queue.pop_front()?;
+        if line.is_marker() {
+            return Some(line);
+        }
+        let number = self.lines;
+        self.lines += 1;
+        Some(BufLine::content(number, line.into_text()))
+    }
+
+    /// Whether every file was read up to its end once.
+    pub fn is_loaded(&self) -> bool {
+        self.sources.iter().all(|source| source.loaded)
+    }
+}
+
+// The file name without the directories leading to it.
+fn short_name(name: &str) -> &str {
+    Path::new(name)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(name)
+}
@@ -0,0 +1,897 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2024 Mohammed Rehaan and contributors
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ * */
+
+use std::fs::{File, Metadata};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom};
+use std::mem::{replace, take};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, ChildStderr, ChildStdout, ExitStatus, Stdio};
+use std::sync::mpsc::{
+    channel, sync_channel, Receiver, RecvTimeoutError, Sender, SyncSender, TryIter, TryRecvError,
+};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+
+use crate::status::Notice;
+
+// Lines sent to the ui in one go.
+const BATCH_SIZE: usize = 4096;
+// Checkpoints sent to the ui in one go when indexing.
+const CHECKPOINT_BATCH: usize = 8192;
+/// Every n-th line has its start offset recorded in the index.
+pub const INDEX_STRIDE: usize = 128;
+// Bytes read at a time when looking for lines from the end of a file.
+const SEEK_BLOCK: usize = 64 * 1024;
+// Batches that may be in flight before the reader waits for the ui to catch up.
+const BATCH_BOUND: usize = 16;
+// Wait between checks for a command to exit after its output was closed.
+const EXIT_POLL: Duration = Duration::from_millis(50);
+
+/// Shown below the last line once the writer of a stream has closed it.
+pub const STREAM_END: &str = "──── end of stream ────";
+
+/// Identifies the file behind a path, as `(device, inode)` on unix.
+type FileId = (u64, u64);
+
+#[cfg(unix)]
+fn file_id(meta: &Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &Metadata) -> Option<FileId> {
+    None
+}
+
+/// Whether the path leads to a named pipe rather than a regular file.
+#[cfg(unix)]
+pub fn is_fifo(meta: &Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    meta.file_type().is_fifo()
+}
+
+#[cfg(not(unix))]
+pub fn is_fifo(_meta: &Metadata) -> bool {
+    false
+}
+
+/// What a [`Worker`] reads lines from.
+pub enum Source {
+    /// A regular file, followed by name when it is rotated or truncated.
+    File(File),
+    /// Standard input, read for as long as it is written to.
+    Stdin,
+    /// A named pipe, opened again for each writer that shows up until the worker is dropped.
+    Fifo(PathBuf),
+    /// The output of a shell command, both stdout and stderr, until it exits.
+    Command(String),
+    /// The output of a shell command that is run again every so often, like `watch`.
+    Watch(String, Duration),
+}
+
+/// Happened to the file on disk while it was tailed.
+#[derive(Clone, Copy)]
+pub enum FileEvent {
+    Rotated,
+    Truncated,
+    Deleted,
+}
+
+impl FileEvent {
+    /// Tell the user that this happened to the file called `name`.
+    pub fn notice(self, name: &str) -> Notice {
+        match self {
+            FileEvent::Rotated => Notice::info(format!("{name} was rotated, reopened it")),
+            FileEvent::Truncated => Notice::info(format!("{name} was truncated, reading it again")),
+            FileEvent::Deleted => Notice::warn(format!("{name} was deleted")),
+        }
+    }
+}
+
+/// Sent from the reader thread to the file buffer.
+pub enum Chunk {
+    Lines(Vec<String>),
+    Marker(&'static str),
+    /// Complete lines found since the last chunk and the start offsets of
+    /// every [`INDEX_STRIDE`]-th line among them.
+    Index {
+        lines: usize,
+        checkpoints: Vec<u64>,
+    },
+    /// The indexed file was rotated or truncated, index it again from the start
+    /// of this file.
+    Restart(File, &'static str),
+    /// The file was read up to EOF for the first time.
+    Loaded,
+    Event(FileEvent),
+    /// The command that was read from has finished.
+    Exited(ExitStatus),
+    /// The whole output of a watched command from its latest run, replacing the previous one.
+    Snapshot(Vec<String>, ExitStatus),
+    Error(String),
+}
+
+/// Sent from the file buffer to the reader thread.
+#[derive(Clone, Copy)]
+pub enum Command {
+    /// The file changed on disk.
+    Wake,
+    Tail(bool),
+}
+
+/// Handle to a thread reading a single file in the background.
+///
+/// The thread exits once the handle is dropped, killing the command it reads from if any.
+pub struct Worker {
+    commands: Sender<Command>,
+    chunks: Receiver<Chunk>,
+    child: Running,
+    // Named pipe the thread may be waiting on for a writer.
+    fifo: Option<PathBuf>,
+}
+
+/// The command currently run for a worker, if any.
+type Running = Arc<Mutex<Option<Child>>>;
+
+impl Worker {
+    /// Read `source` from its current position onwards. Streams are never indexed and
+    /// always followed, they cannot be read again or looked at for rotation.
+    pub fn spawn(
+        origin: &Path,
+        source: Source,
+        is_tail: bool,
+        indexed: bool,
+    ) -> anyhow::Result<Self> {
+        let (commands, command_rx) = channel();
+        let (chunk_tx, chunks) = sync_channel(BATCH_BOUND);
+        let name = format!("reader {}", origin.display());
+        let child = Running::default();
+        let running = child.clone();
+
+        let mut file = match source {
+            Source::File(file) => file,
+            Source::Command(command) => {
+                let output = start(&command, &running)?;
+                thread::Builder::new()
+                    .name(name)
+                    .spawn(move || run_command(output, running, chunk_tx))?;
+                return Ok(Worker {
+                    commands,
+                    chunks,
+                    child,
+                    fifo: None,
+                });
+            }
+            Source::Watch(command, interval) => {
+                thread::Builder::new()
+                    .name(name)
+                    .spawn(move || run_watch(&command, interval, running, command_rx, chunk_tx))?;
+                return Ok(Worker {
+                    commands,
+                    chunks,
+                    child,
+                    fifo: None,
+                });
+            }
+            stream => {
+                let fifo = match &stream {
+                    Source::Fifo(path) => Some(path.clone()),
+                    _ => None,
+                };
+                thread::Builder::new()
+                    .name(name)
+                    .spawn(move || run_stream(stream, command_rx, chunk_tx))?;
+                return Ok(Worker {
+                    commands,
+                    chunks,
+                    child,
+                    fifo,
+                });
+            }
+        };
+        let offset = file.stream_position()?;
+
+        let reader = Reader {
+            origin: origin.to_path_buf(),
+            file_id: file_id(&file.metadata()?),
+            reader: Some(BufReader::new(file)),
+            offset,
+            partial: Vec::new(),
+            is_tail,
+            indexed,
+            indexed_lines: 0,
+            line_start: offset,
+            loaded: false,
+            missing: false,
+            chunks: chunk_tx,
+        };
+
+        thread::Builder::new()
+            .name(name)
+            .spawn(move || reader.run(command_rx))?;
+
+        Ok(Worker {
+            commands,
+            chunks,
+            child,
+            fifo: None,
+        })
+    }
+
+    pub fn send(&self, command: Command) {
+        // The thread only hangs up after reporting an error, which is received anyway.
+        let _ = self.commands.send(command);
+    }
+
+    pub fn chunks(&self) -> TryIter<'_, Chunk> {
+        self.chunks.try_iter()
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        stop(&self.child);
+        if let Some(path) = self.fifo.as_ref() {
+            // Hang up first so the thread finds the buffer closed once it is woken
+            // rather than waiting for the next writer.
+            drop(replace(&mut self.commands, channel().0));
+            wake_fifo(path);
+        }
+    }
+}
+
+// Kill the command if it is still running.
+fn stop(running: &Running) {
+    let Ok(mut child) = running.lock() else {
+        return;
+    };
+    let Some(child) = child.as_mut() else {
+        return;
+    };
+    // Once the command was waited for its id may already belong to another process.
+    if let Ok(None) = child.try_wait() {
+        kill(child);
+    }
+}
+
+struct Reader {
+    // Path as given by the user, reopened by name when the file is rotated.
+    origin: PathBuf,
+    // Identity of the file held by the reader.
+    file_id: Option<FileId>,
+    reader: Option<BufReader<File>>,
+    // Bytes consumed so far, used to resume reading after the reader is dropped.
+    offset: u64,
+    // Incomplete trailing line that is still being written to, kept as bytes so that
+    // content which is not valid UTF-8 is shown rather than failing the file.
+    partial: Vec<u8>,
+    is_tail: bool,
+    // Only line offsets are collected, the content is read by the ui on demand.
+    indexed: bool,
+    // Complete lines indexed since the file was last restarted.
+    indexed_lines: usize,
+    // Offset of the line that is currently being indexed.
+    line_start: u64,
+    loaded: bool,
+    // The path was found to be gone, reported once until it is back.
+    missing: bool,
+    chunks: SyncSender<Chunk>,
+}
+
+impl Reader {
+    fn run(mut self, commands: Receiver<Command>) {
+        loop {
+            if let Err(err) = self.read() {
+                let _ = self.chunks.send(Chunk::Error(format!("{err}")));
+                return;
+            }
+
+            let Ok(command) = commands.recv() else {
+                return;
+            };
+            for command in std::iter::once(command).chain(commands.try_iter()) {
+                if let Command::Tail(is_tail) = command {
+                    self.is_tail = is_tail;
+                }
+            }
+        }
+    }
+
+    fn read(&mut self) -> anyhow::Result<()> {
+        if self.is_tail && self.reader.is_none() {
+            self.follow_name()?;
+        }
+
+        self.read_available()?;
+        // Keep going for as long as the file gets swapped out underneath.
+        while self.is_tail && self.follow_name()? {
+            self.read_available()?;
+        }
+
+        if !self.loaded {
+            self.loaded = true;
+            self.send(Chunk::Loaded)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_available(&mut self) -> anyhow::Result<()> {
+        if self.indexed {
+            return self.index_available();
+        }
+
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(());
+        };
+
+        let mut batch = Vec::new();
+        loop {
+            let read = reader.read_until(b'\n', &mut self.partial)?;
+            self.offset += read as u64;
+
+            if read == 0 {
+                break;
+            }
+            // A line without a newline is still being written; wait for the rest.
+            if !self.partial.ends_with(b"\n") {
+                continue;
+            }
+
+            batch.push(clean(&String::from_utf8_lossy(&self.partial)));
+            self.partial.clear();
+
+            if batch.len() == BATCH_SIZE {
+                self.chunks
+                    .send(Chunk::Lines(take(&mut batch)))
+                    .map_err(|_| anyhow!("File buffer was closed"))?;
+            }
+        }
+
+        // Files that are not tailed are read once and closed.
+        if !self.is_tail {
+            self.reader = None;
+            if !self.partial.is_empty() {
+                batch.push(clean(&String::from_utf8_lossy(&take(&mut self.partial))));
+            }
+        }
+
+        if !batch.is_empty() {
+            self.send(Chunk::Lines(batch))?;
+        }
+
+        Ok(())
+    }
+
+    fn index_available(&mut self) -> anyhow::Result<()> {
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(());
+        };
+
+        let mut lines = 0;
+        let mut checkpoints = Vec::new();
+        loop {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+
+            for (pos, _) in buf.iter().enumerate().filter(|(_, &b)| b == b'\n') {
+                self.line_start = self.offset + pos as u64 + 1;
+                self.indexed_lines += 1;
+                lines += 1;
+                if self.indexed_lines.is_multiple_of(INDEX_STRIDE) {
+                    checkpoints.push(self.line_start);
+                }
+            }
+
+            let len = buf.len();
+            reader.consume(len);
+            self.offset += len as u64;
+
+            if checkpoints.len() >= CHECKPOINT_BATCH {
+                let checkpoints = take(&mut checkpoints);
+                self.chunks
+                    .send(Chunk::Index { lines, checkpoints })
+                    .map_err(|_| anyhow!("File buffer was closed"))?;
+                lines = 0;
+            }
+        }
+
+        // Files that are not tailed are read once and closed.
+        if !self.is_tail {
+            self.reader = None;
+            if self.offset > self.line_start {
+                self.line_start = self.offset;
+                lines += 1;
+            }
+        }
+
+        if lines > 0 {
+            self.send(Chunk::Index { lines, checkpoints })?;
+        }
+
+        Ok(())
+    }
+
+    // Make sure the reader holds the file currently found at the original path,
+    // reopening it by name when it has been rotated, replaced or recreated.
+    // Returns whether the reader now starts over on a different file.
+    fn follow_name(&mut self) -> anyhow::Result<bool> {
+        let meta = match std::fs::metadata(&self.origin) {
+            Ok(meta) => meta,
+            // Wait for the file to reappear, the old reader may still receive writes.
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                if !self.missing {
+                    self.missing = true;
+                    self.send(Chunk::Event(FileEvent::Deleted))?;
+                }
+                return Ok(false);
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        self.missing = false;
+        let id = file_id(&meta);
+        if id == self.file_id && meta.len() < self.offset {
+            self.truncated()?;
+            return Ok(true);
+        }
+        if id == self.file_id {
+            if self.reader.is_none() {
+                let mut file = File::open(&self.origin)?;
+                file.seek(SeekFrom::Start(self.offset))?;
+                self.reader = Some(BufReader::new(file));
+            }
+            return Ok(false);
+        }
+
+        log::trace!("File {} was rotated, reopening", self.origin.display());
+
+        let file = File::open(&self.origin)?;
+        self.file_id = file_id(&file.metadata()?);
+        self.reader = Some(BufReader::new(file));
+        self.restart("──── file rotated ────")?;
+        self.send(Chunk::Event(FileEvent::Rotated))?;
+
+        Ok(true)
+    }
+
+    // The file shrank below what was already read, e.g. `copytruncate` or `> file`.
+    // Start over from the beginning while keeping the line numbers going.
+    fn truncated(&mut self) -> anyhow::Result<()> {
+        log::trace!("File {} was truncated, rewinding", self.origin.display());
+
+        match self.reader.as_mut() {
+            Some(reader) => {
+                reader.seek(SeekFrom::Start(0))?;
+            }
+            None => self.reader = Some(BufReader::new(File::open(&self.origin)?)),
+        }
+        self.restart("──── file truncated ────")?;
+        self.send(Chunk::Event(FileEvent::Truncated))?;
+
+        Ok(())
+    }
+
+    // Mark the switch to a new file or the start of the same one.
+    fn restart(&mut self, marker: &'static str) -> anyhow::Result<()> {
+        self.offset = 0;
+
+        if self.indexed {
+            self.indexed_lines = 0;
+            self.line_start = 0;
+            return self.send(Chunk::Restart(File::open(&self.origin)?, marker));
+        }
+
+        if !self.partial.is_empty() {
+            let line = clean(&String::from_utf8_lossy(&take(&mut self.partial)));
+            self.send(Chunk::Lines(vec![line]))?;
+        }
+        self.send(Chunk::Marker(marker))
+    }
+
+    fn send(&self, chunk: Chunk) -> anyhow::Result<()> {
+        self.chunks
+            .send(chunk)
+            .map_err(|_| anyhow!("File buffer was closed"))
+    }
+}
+
+fn run_stream(source: Source, commands: Receiver<Command>, chunks: SyncSender<Chunk>) {
+    let send = |chunk| {
+        chunks
+            .send(chunk)
+            .map_err(|_| anyhow!("File buffer was closed"))
+    };
+    let read = || -> anyhow::Result<()> {
+        let input: Box<dyn Read> = match source {
+            Source::File(file) => Box::new(file),
+            Source::Stdin => Box::new(std::io::stdin()),
+            Source::Fifo(path) => return read_fifo(&path, &commands, &chunks),
+            Source::Command(_) | Source::Watch(..) => {
+                return Err(anyhow!("Commands are run rather than streamed"))
+            }
+        };
+        // There is no end to read up to, only what has been written so far.
+        send(Chunk::Loaded)?;
+        read_stream(input, &chunks)?;
+        send(Chunk::Marker(STREAM_END))
+    };
+
+    if let Err(err) = read() {
+        let _ = chunks.send(Chunk::Error(format!("{err}")));
+    }
+}
+
+// A named pipe ends each time its last writer closes it, so it is opened again and
+// waited on for the next one until the buffer is closed.
+fn read_fifo(
+    path: &Path,
+    commands: &Receiver<Command>,
+    chunks: &SyncSender<Chunk>,
+) -> anyhow::Result<()> {
+    let mut loaded = false;
+    loop {
+        let fifo = File::open(path)?;
+        if is_closed(commands) {
+            return Ok(());
+        }
+        if !loaded {
+            loaded = true;
+            chunks
+                .send(Chunk::Loaded)
+                .map_err(|_| anyhow!("File buffer was closed"))?;
+        }
+        read_stream(fifo, chunks)?;
+    }
+}
+
+// Whether the buffer hung up, streams have no use for the commands themselves.
+fn is_closed(commands: &Receiver<Command>) -> bool {
+    loop {
+        match commands.try_recv() {
+            Ok(_) => continue,
+            Err(err) => return err == TryRecvError::Disconnected,
+        }
+    }
+}
+
+// Open the named pipe for writing and close it straight away, which lets a reader
+// waiting for a writer go on. Does nothing when no one is reading it.
+#[cfg(unix)]
+fn wake_fifo(path: &Path) {
+    use std::os::unix::fs::OpenOptionsExt;
+    let _ = std::fs::OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path);
+}
+
+#[cfg(not(unix))]
+fn wake_fifo(_path: &Path) {}
+
+// Read the output of a command as it is written, stderr on a thread of its own, then
+// wait for the command to exit.
+fn run_command(
+    (stdout, stderr): (Option<ChildStdout>, Option<ChildStderr>),
+    running: Running,
+    chunks: SyncSender<Chunk>,
+) {
+    let errors = chunks.clone();
+    let stderr = thread::spawn(move || match stderr {
+        Some(stderr) => read_stream(stderr, &errors),
+        None => Ok(()),
+    });
+
+    let read = || -> anyhow::Result<()> {
+        chunks
+            .send(Chunk::Loaded)
+            .map_err(|_| anyhow!("File buffer was closed"))?;
+        if let Some(stdout) = stdout {
+            read_stream(stdout, &chunks)?;
+        }
+        stderr
+            .join()
+            .map_err(|_| anyhow!("Reading stderr panicked"))?
+    };
+    // The command is waited for even when reading failed or the buffer was closed,
+    // killing it first if reading stopped early, so it does not linger as a zombie.
+    let read = read();
+    if read.is_err() {
+        stop(&running);
+    }
+    let chunk = match (read, wait(&running)) {
+        (Err(err), _) | (_, Err(err)) => Chunk::Error(format!("{err}")),
+        (Ok(()), Ok(status)) => Chunk::Exited(status),
+    };
+    let _ = chunks.send(chunk);
+}
+
+// Run the command every `interval` until the buffer is closed, sending its whole output each time.
+fn run_watch(
+    command: &str,
+    interval: Duration,
+    running: Running,
+    commands: Receiver<Command>,
+    chunks: SyncSender<Chunk>,
+) {
+    loop {
+        let chunk = match watch_once(command, &running) {
+            Ok((lines, status)) => Chunk::Snapshot(lines, status),
+            Err(err) => Chunk::Error(format!("{err}")),
+        };
+        let failed = matches!(chunk, Chunk::Error(_));
+        if chunks.send(chunk).is_err() || failed {
+            return;
+        }
+
+        // Nothing the buffer sends changes when the command runs next.
+        let next = Instant::now() + interval;
+        loop {
+            match commands.recv_timeout(next.saturating_duration_since(Instant::now())) {
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
+
+// Run the command to completion and collect its output, stderr after stdout.
+fn watch_once(command: &str, running: &Running) -> anyhow::Result<(Vec<String>, ExitStatus)> {
+    let (stdout, stderr) = start(command, running)?;
+    let stderr = thread::spawn(move || read_all(stderr));
+    let read = || -> anyhow::Result<Vec<String>> {
+        let mut lines = read_all(stdout)?;
+        lines.extend(
+            stderr
+                .join()
+                .map_err(|_| anyhow!("Reading stderr panicked"))??,
+        );
+        Ok(lines)
+    };
+    // Waited for whatever happens while reading, as in `run_command`.
+    let read = read();
+    if read.is_err() {
+        stop(running);
+    }
+    let status = wait(running);
+    Ok((read?, status?))
+}
+
+fn read_all(input: Option<impl Read>) -> anyhow::Result<Vec<String>> {
+    let mut bytes = Vec::new();
+    if let Some(mut input) = input {
+        input.read_to_end(&mut bytes)?;
+    }
+    Ok(String::from_utf8_lossy(&bytes).lines().map(clean).collect())
+}
+
+// Run the command in the background, keeping hold of it so it can be stopped with the worker.
+fn start(
+    command: &str,
+    running: &Running,
+) -> anyhow::Result<(Option<ChildStdout>, Option<ChildStderr>)> {
+    let mut child = shell(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let output = (child.stdout.take(), child.stderr.take());
+    *running
+        .lock()
+        .map_err(|_| anyhow!("Command handle was poisoned"))? = Some(child);
+    Ok(output)
+}
+
+// Wait for the command to exit. Both pipes are closed by then, unless it handed them on.
+fn wait(running: &Running) -> anyhow::Result<ExitStatus> {
+    loop {
+        let status = running
+            .lock()
+            .map_err(|_| anyhow!("Command handle was poisoned"))?
+            .as_mut()
+            .map(Child::try_wait)
+            .transpose()?
+            .flatten();
+        if let Some(status) = status {
+            return Ok(status);
+        }
+        thread::sleep(EXIT_POLL);
+    }
+}
+
+// Read lines as they are written until the writer closes the stream. Lines are passed on
+// as soon as nothing more is buffered, rather than in full batches, since more may take a while.
+fn read_stream(input: impl Read, chunks: &SyncSender<Chunk>) -> anyhow::Result<()> {
+    let send = |chunk| {
+        chunks
+            .send(chunk)
+            .map_err(|_| anyhow!("File buffer was closed"))
+    };
+
+    // Read as bytes so that output which is not valid UTF-8 is shown rather than failing.
+    let mut reader = BufReader::new(input);
+    let mut partial = Vec::new();
+    let mut batch = Vec::new();
+    loop {
+        if reader.buffer().is_empty() && !batch.is_empty() {
+            send(Chunk::Lines(take(&mut batch)))?;
+        }
+
+        if reader.read_until(b'\n', &mut partial)? == 0 {
+            break;
+        }
+        if !partial.ends_with(b"\n") {
+            continue;
+        }
+        batch.push(clean(&String::from_utf8_lossy(&take(&mut partial))));
+        if batch.len() == BATCH_SIZE {
+            send(Chunk::Lines(take(&mut batch)))?;
+        }
+    }
+
+    if !partial.is_empty() {
+        batch.push(clean(&String::from_utf8_lossy(&partial)));
+    }
+    if !batch.is_empty() {
+        send(Chunk::Lines(batch))?;
+    }
+    Ok(())
+}
+
+// The command gets a process group of its own so whatever it starts can be stopped with it.
+#[cfg(unix)]
+fn shell(command: &str) -> process::Command {
+    use std::os::unix::process::CommandExt;
+    let mut shell = process::Command::new("sh");
+    shell.arg("-c").arg(command).process_group(0);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> process::Command {
+    let mut shell = process::Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(unix)]
+fn kill(child: &mut Child) {
+    // SAFETY: `killpg` only sends a signal, the group was created for the child by `shell`.
+    unsafe {
+        libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) {
+    // Fails when the command has already exited, which is fine.
+    let _ = child.kill();
+}
+
+/// Move `file` to the start of its last `lines` lines, like `tail -n`, by
+/// reading backwards from the end. Returns the new position.
+pub fn seek_last_lines(file: &mut File, lines: usize) -> std::io::Result<u64> {
+    let len = file.metadata()?.len();
+    let mut pos = len;
+    let mut found = 0;
+    let mut block = vec![0; SEEK_BLOCK];
+
+    let start = 'search: {
+        if lines == 0 {
+            break 'search len;
+        }
+        while pos > 0 {
+            let size = pos.min(SEEK_BLOCK as u64) as usize;
+            pos -= size as u64;
+            file.seek(SeekFrom::Start(pos))?;
+            file.read_exact(&mut block[..size])?;
+
+            for (i, _) in block[..size]
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(_, &b)| b == b'\n')
+            {
+                let line_start = pos + i as u64 + 1;
+                // The newline ending the file does not start another line.
+                if line_start == len {
+                    continue;
+                }
+                found += 1;
+                if found == lines {
+                    break 'search line_start;
+                }
+            }
+        }
+        0
+    };
+
+    file.seek(SeekFrom::Start(start))
+}
+
+pub fn clean(line: &str) -> String {
+    line.replace('\t', &"\u{000A0}".repeat(4))
+        .replace(['\r', '\n'], "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // What is left to read after seeking to the last `lines` lines of `content`.
+    fn last_lines(name: &str, content: &[u8], lines: usize) -> Vec<u8> {
+        let path = std::env::temp_dir().join(format!("refer-{}-{name}", process::id()));
+        File::create(&path).unwrap().write_all(content).unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        let start = seek_last_lines(&mut file, lines).unwrap();
+        let mut rest = Vec::new();
+        file.read_to_end(&mut rest).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(start as usize, content.len() - rest.len());
+        rest
+    }
+
+    #[test]
+    fn seek_last_lines_with_trailing_newline() {
+        assert_eq!(last_lines("trailing", b"a\nb\nc\n", 2), b"b\nc\n");
+        assert_eq!(last_lines("trailing-all", b"a\nb\nc\n", 3), b"a\nb\nc\n");
+        assert_eq!(last_lines("trailing-more", b"a\nb\nc\n", 10), b"a\nb\nc\n");
+    }
+
+    #[test]
+    fn seek_last_lines_without_trailing_newline() {
+        assert_eq!(last_lines("partial", b"a\nb\nc", 2), b"b\nc");
+        assert_eq!(last_lines("partial-one", b"a\nb\nc", 1), b"c");
+        assert_eq!(last_lines("partial-more", b"a\nb\nc", 10), b"a\nb\nc");
+    }
+
+    #[test]
+    fn seek_last_lines_none() {
+        assert_eq!(last_lines("none", b"a\nb\n", 0), b"");
+        assert_eq!(last_lines("none-partial", b"a\nb", 0), b"");
+        assert_eq!(last_lines("empty", b"", 3), b"");
+    }
+
+    #[test]
+    fn seek_last_lines_across_blocks() {
+        let line = format!("{}\n", "x".repeat(99));
+        let content = line.repeat(SEEK_BLOCK / 100 * 3).into_bytes();
+        let lines = SEEK_BLOCK / 100 * 2;
+        assert_eq!(
+            last_lines("blocks", &content, lines),
+            line.repeat(lines).as_bytes()
+        );
+    }
+}
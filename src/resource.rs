@@ -24,30 +24,61 @@
  * */
 
 use std::cell::{Ref, RefCell, RefMut};
+use std::io::{stdin, IsTerminal};
+use std::time::Duration;
 
 use clap::Parser;
 
 use crate::cursor::*;
 use crate::input::*;
 use crate::io::*;
+use crate::status::StatusBar;
+use crate::time::TimeFormat;
 
 pub struct Resource {
     pub pointer: KeyboardCursor,
     pub entry_box: EntryBox,
     pub file_list_state: RefCell<FileListState>,
     pub files: FileList,
+    // Count typed before a motion, like `20j`.
+    pub count: Option<usize>,
+    pub panes: Panes,
+    // The scrollbar is being dragged with the mouse.
+    pub dragging: bool,
+    pub status: StatusBar,
 }
 
 impl Resource {
     pub fn new() -> anyhow::Result<Self> {
-        let args = Refer::parse();
-        let files = FileList::with_files(args.filename)?;
+        let mut args = Refer::parse();
+        // Piped input is shown without having to ask for it, keys are still read from the terminal.
+        if !stdin().is_terminal() && !args.filename.iter().any(|name| name == STDIN) {
+            args.filename.insert(0, STDIN.to_string());
+        }
+        let options = BufOptions {
+            index_threshold: args.index_threshold.saturating_mul(1024 * 1024),
+            tail_lines: args.lines.or(args.start_at_end.then_some(TAIL_LINES)),
+            max_lines: args.max_lines,
+            max_bytes: args.max_bytes,
+            time_format: args
+                .time_format
+                .as_deref()
+                .map(TimeFormat::custom)
+                .transpose()?,
+            retry: args.retry,
+            watch: args.watch.map(Duration::try_from_secs_f64).transpose()?,
+        };
+        let files = FileList::with_files(args.filename, options);
 
         Ok(Resource {
             pointer: KeyboardCursor::new(),
             entry_box: EntryBox::new(),
             file_list_state: RefCell::new(FileListState::new(files.len())),
             files,
+            count: None,
+            panes: Panes::default(),
+            dragging: false,
+            status: StatusBar::default(),
         })
     }
 
@@ -62,7 +93,7 @@ impl Resource {
     }
 
     #[inline]
-    pub fn file_list_state(&self) -> Ref<FileListState> {
+    pub fn file_list_state(&self) -> Ref<'_, FileListState> {
         self.file_list_state.borrow()
     }
 
@@ -82,7 +113,7 @@ impl Resource {
     }
 
     #[inline]
-    pub fn file_list_state_mut(&self) -> RefMut<FileListState> {
+    pub fn file_list_state_mut(&self) -> RefMut<'_, FileListState> {
         self.file_list_state.borrow_mut()
     }
 
@@ -90,18 +121,74 @@ impl Resource {
     pub fn files_mut(&mut self) -> &mut FileList {
         &mut self.files
     }
+
+    pub fn push_count(&mut self, digit: u32) {
+        let count = self.count.unwrap_or_default();
+        self.count = Some(count.saturating_mul(10).saturating_add(digit as usize));
+    }
+
+    #[inline]
+    pub fn count(&self) -> Option<usize> {
+        self.count
+    }
+
+    #[inline]
+    pub fn take_count(&mut self) -> Option<usize> {
+        self.count.take()
+    }
+
+    #[inline]
+    pub fn panes(&self) -> Panes {
+        self.panes
+    }
+
+    #[inline]
+    pub fn status(&self) -> &StatusBar {
+        &self.status
+    }
+
+    #[inline]
+    pub fn status_mut(&mut self) -> &mut StatusBar {
+        &mut self.status
+    }
 }
 
 #[derive(Parser)]
 #[command(about, long_about=None)]
 struct Refer {
+    /// Files to open, `-` reads standard input.
     filename: Vec<String>,
+    /// Files of at least this many MiB are indexed and read from the disk on demand.
+    #[arg(long, value_name = "MIB", default_value_t = INDEX_THRESHOLD / (1024 * 1024))]
+    index_threshold: u64,
+    /// Start reading each file this many lines before its end, like `tail -n`.
+    #[arg(short = 'n', long, value_name = "N")]
+    lines: Option<usize>,
+    /// Start reading each file near its end rather than from the start.
+    #[arg(short = 'e', long, conflicts_with = "lines")]
+    start_at_end: bool,
+    /// Keep at most this many lines of each file in memory, dropping the oldest.
+    #[arg(long, value_name = "N")]
+    max_lines: Option<usize>,
+    /// Keep at most this many bytes of each file in memory, dropping the oldest lines.
+    #[arg(long, value_name = "BYTES")]
+    max_bytes: Option<usize>,
+    /// Look for timestamps written in this `strftime` format at the start of
+    /// lines before the common formats, e.g. "%d.%m.%Y %H:%M:%S".
+    #[arg(long, value_name = "FORMAT")]
+    time_format: Option<String>,
+    /// Keep trying to reopen files that cannot be read, waiting longer after each attempt.
+    #[arg(long)]
+    retry: bool,
+    /// Run `!command` buffers again every this many seconds, like `watch -n`, highlighting
+    /// the lines that changed since the run before.
+    #[arg(short = 'w', long, value_name = "SECONDS")]
+    watch: Option<f64>,
 }
 
 pub fn state_update(res: &mut Resource) {
-    res.files_mut().iter_mut().for_each(|f| {
-        if let Err(err) = f.update() {
-            f.nullify(format!("{err}"));
-        }
-    });
+    for notice in res.files_mut().update() {
+        res.status_mut().push(notice);
+    }
+    res.status_mut().expire();
 }
@@ -0,0 +1,359 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2024 Mohammed Rehaan and contributors
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ * */
+
+use regex::{Regex, RegexBuilder};
+
+use crate::utils::strip_ansi;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    Regex,
+    Literal,
+}
+
+impl SearchMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            SearchMode::Regex => SearchMode::Literal,
+            SearchMode::Literal => SearchMode::Regex,
+        }
+    }
+}
+
+// Patterns are matched case insensitively unless they contain an uppercase letter.
+fn build_regex(pattern: &str, mode: SearchMode) -> anyhow::Result<Regex> {
+    let escaped = match mode {
+        SearchMode::Regex => pattern.to_string(),
+        SearchMode::Literal => regex::escape(pattern),
+    };
+    let regex = RegexBuilder::new(&escaped)
+        .case_insensitive(!pattern.chars().any(char::is_uppercase))
+        .build()?;
+    Ok(regex)
+}
+
+/// Matches of a pattern within a file buffer.
+///
+/// The buffer is scanned a step at a time as it is read so searching never
+/// holds up the ui, matches are recorded by their position in the buffer.
+pub struct Search {
+    regex: Regex,
+    matches: Vec<usize>,
+    current: Option<usize>,
+    // Entries up to here have been looked at.
+    scanned: usize,
+    // Jump to the first match at or after this position once it is found.
+    origin: Option<usize>,
+}
+
+impl Search {
+    /// The pattern is matched case insensitively unless it contains an uppercase letter.
+    pub fn new(pattern: &str, mode: SearchMode, origin: usize) -> anyhow::Result<Self> {
+        Ok(Search {
+            regex: build_regex(pattern, mode)?,
+            matches: Vec::new(),
+            current: None,
+            scanned: 0,
+            origin: Some(origin),
+        })
+    }
+
+    #[inline]
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+
+    #[inline]
+    pub fn scanned(&self) -> usize {
+        self.scanned
+    }
+
+    /// Look at the entry at the next position to scan, `None` for markers.
+    /// Returns the position to jump to when it is the first match past the origin.
+    pub fn scan(&mut self, text: Option<&str>) -> Option<usize> {
+        let pos = self.scanned;
+        self.scanned += 1;
+
+        let text = text?;
+        if !self.regex.is_match(&strip_ansi(text)) {
+            return None;
+        }
+        self.matches.push(pos);
+
+        let origin = self.origin?;
+        if pos < origin {
+            return None;
+        }
+        self.origin = None;
+        self.current = Some(self.matches.len() - 1);
+        Some(pos)
+    }
+
+    /// Called once everything there is has been scanned. With no match past
+    /// the origin the search wraps around to the first one.
+    pub fn scanned_all(&mut self) -> Option<usize> {
+        self.origin?;
+        let first = *self.matches.first()?;
+        self.origin = None;
+        self.current = Some(0);
+        Some(first)
+    }
+
+    /// Forget the entries before `end` after they were dropped from the buffer,
+    /// which moved everything after them back by `removed`.
+    pub fn shift(&mut self, end: usize, removed: usize) {
+        let dropped = self.matches.partition_point(|&pos| pos < end);
+        self.matches.drain(..dropped);
+        self.matches.iter_mut().for_each(|pos| *pos -= removed);
+        self.current = self
+            .current
+            .and_then(|current| current.checked_sub(dropped));
+        self.scanned = self.scanned.saturating_sub(removed);
+    }
+
+    /// Start over after the buffer was replaced.
+    pub fn restart(&mut self) {
+        self.matches.clear();
+        self.current = None;
+        self.scanned = 0;
+    }
+
+    /// Move to the match after `from`, or the one after the current match.
+    /// Returns its position and whether the search wrapped around.
+    pub fn next(&mut self, from: usize) -> Option<(usize, bool)> {
+        if self.matches.is_empty() {
+            return None;
+        }
+        let next = match self.current {
+            Some(current) => current + 1,
+            None => self.matches.partition_point(|&pos| pos < from),
+        };
+        let wrapped = next >= self.matches.len();
+        let next = if wrapped { 0 } else { next };
+
+        self.current = Some(next);
+        self.origin = None;
+        Some((self.matches[next], wrapped))
+    }
+
+    /// Move to the match before `from`, or the one before the current match.
+    /// Returns its position and whether the search wrapped around.
+    pub fn prev(&mut self, from: usize) -> Option<(usize, bool)> {
+        let prev = match self.current {
+            Some(current) => current.checked_sub(1),
+            None => self
+                .matches
+                .partition_point(|&pos| pos < from)
+                .checked_sub(1),
+        };
+        let wrapped = prev.is_none();
+        let prev = prev.unwrap_or(self.matches.len().checked_sub(1)?);
+
+        self.current = Some(prev);
+        self.origin = None;
+        Some((self.matches[prev], wrapped))
+    }
+
+    /// The current match counting from one, and the number of matches found so far.
+    pub fn position(&self) -> (Option<usize>, usize) {
+        (self.current.map(|current| current + 1), self.matches.len())
+    }
+}
+
+/// Entries of a file buffer that pass a filter, like `grep`.
+///
+/// The view scrolls through the positions kept by the filter rather than the
+/// buffer itself. Markers are always kept so events such as rotation still show.
+pub struct Filter {
+    regex: Regex,
+    // The expression as typed, shown next to the file name.
+    expr: Box<str>,
+    mode: SearchMode,
+    // Keep the lines that do not match instead, like `grep -v`.
+    invert: bool,
+    positions: Vec<usize>,
+    // Entries up to here have been looked at.
+    scanned: usize,
+}
+
+impl Filter {
+    /// A leading `!` inverts the filter, the rest is matched like a search.
+    pub fn new(expr: &str, mode: SearchMode) -> anyhow::Result<Self> {
+        let (invert, pattern) = match expr.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, expr),
+        };
+
+        Ok(Filter {
+            regex: build_regex(pattern, mode)?,
+            expr: expr.into(),
+            mode,
+            invert,
+            positions: Vec::new(),
+            scanned: 0,
+        })
+    }
+
+    #[inline]
+    pub fn expr(&self) -> &str {
+        &self.expr
+    }
+
+    #[inline]
+    pub fn mode(&self) -> SearchMode {
+        self.mode
+    }
+
+    #[inline]
+    pub fn scanned(&self) -> usize {
+        self.scanned
+    }
+
+    /// Positions in the buffer of the entries kept so far.
+    #[inline]
+    pub fn positions(&self) -> &[usize] {
+        &self.positions
+    }
+
+    /// Look at the entry at the next position to scan, `None` for markers.
+    pub fn scan(&mut self, text: Option<&str>) {
+        let pos = self.scanned;
+        self.scanned += 1;
+
+        let keep = match text {
+            Some(text) => self.regex.is_match(&strip_ansi(text)) != self.invert,
+            None => true,
+        };
+        if keep {
+            self.positions.push(pos);
+        }
+    }
+
+    /// Forget the entries before `end` after they were dropped from the buffer,
+    /// which moved everything after them back by `removed`. The dropped entries
+    /// are replaced by a note at the front of the buffer.
+    /// Returns the same as seen through the filter.
+    pub fn shift(&mut self, end: usize, removed: usize) -> (usize, usize) {
+        let dropped = self.positions.partition_point(|&pos| pos < end);
+        self.positions.drain(..dropped);
+        self.positions.iter_mut().for_each(|pos| *pos -= removed);
+        self.scanned = self.scanned.saturating_sub(removed);
+
+        // The note is a marker so it takes the place of what was dropped,
+        // unless it is yet to be scanned or nothing kept was dropped.
+        if self.scanned == 0 || dropped == 0 {
+            return (dropped, dropped);
+        }
+        self.positions.insert(0, 0);
+        (dropped, dropped - 1)
+    }
+
+    /// Start over after the buffer was replaced.
+    pub fn restart(&mut self) {
+        self.positions.clear();
+        self.scanned = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Entries are matched when they contain an `x`, `None` is a marker.
+    fn search(entries: &[Option<&str>]) -> Search {
+        let mut search = Search::new("x", SearchMode::Literal, 0).unwrap();
+        entries.iter().for_each(|&text| {
+            search.scan(text);
+        });
+        search
+    }
+
+    fn filter(entries: &[Option<&str>]) -> Filter {
+        let mut filter = Filter::new("x", SearchMode::Literal).unwrap();
+        entries.iter().for_each(|&text| filter.scan(text));
+        filter
+    }
+
+    #[test]
+    fn search_shift_keeps_the_current_match() {
+        let mut search = search(&[Some("y"), Some("x"), Some("y"), Some("x"), Some("x")]);
+        search.next(0);
+        assert_eq!(search.position(), (Some(2), 3));
+
+        // The first three entries went and the note took the place of one of them.
+        search.shift(3, 2);
+        assert_eq!(search.matches, [1, 2]);
+        assert_eq!(search.position(), (Some(1), 2));
+        assert_eq!(search.scanned(), 3);
+    }
+
+    #[test]
+    fn search_shift_forgets_a_dropped_current_match() {
+        let mut search = search(&[Some("x"), Some("y"), Some("x")]);
+        assert_eq!(search.position(), (Some(1), 2));
+
+        search.shift(2, 1);
+        assert_eq!(search.matches, [1]);
+        assert_eq!(search.position(), (None, 1));
+    }
+
+    #[test]
+    fn search_shift_before_scanning_the_dropped_entries() {
+        let mut search = search(&[Some("x")]);
+        search.shift(3, 2);
+        assert!(search.matches.is_empty());
+        assert_eq!(search.scanned(), 0);
+    }
+
+    #[test]
+    fn filter_shift_keeps_the_note() {
+        let mut filter = filter(&[Some("x"), Some("y"), Some("x"), Some("x")]);
+        assert_eq!(filter.shift(2, 1), (1, 0));
+        assert_eq!(filter.positions(), [0, 1, 2]);
+        assert_eq!(filter.scanned(), 3);
+    }
+
+    #[test]
+    fn filter_shift_replaces_the_previous_note() {
+        let mut filter = filter(&[None, Some("x"), Some("y"), Some("x")]);
+        assert_eq!(filter.shift(2, 1), (2, 1));
+        assert_eq!(filter.positions(), [0, 2]);
+    }
+
+    #[test]
+    fn filter_shift_with_nothing_kept_dropped() {
+        let mut filter = filter(&[Some("y"), Some("y"), Some("x"), Some("x")]);
+        assert_eq!(filter.shift(2, 1), (0, 0));
+        assert_eq!(filter.positions(), [1, 2]);
+    }
+
+    #[test]
+    fn filter_shift_before_scanning_the_dropped_entries() {
+        let mut filter = filter(&[Some("x")]);
+        assert_eq!(filter.shift(3, 2), (1, 1));
+        assert!(filter.positions().is_empty());
+        assert_eq!(filter.scanned(), 0);
+    }
+}
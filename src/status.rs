@@ -0,0 +1,114 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2024 Mohammed Rehaan and contributors
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ * */
+
+use std::time::{Duration, Instant};
+
+// How long a notice stays in the status bar.
+const NOTICE_DURATION: Duration = Duration::from_secs(4);
+// Errors are kept a while longer so the reason can be read.
+const ERROR_DURATION: Duration = Duration::from_secs(8);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+/// A message shown in the status bar for a short while.
+pub struct Notice {
+    level: Level,
+    text: String,
+    shown: Instant,
+}
+
+impl Notice {
+    pub fn new(level: Level, text: impl Into<String>) -> Self {
+        Notice {
+            level,
+            text: text.into(),
+            shown: Instant::now(),
+        }
+    }
+
+    #[inline]
+    pub fn info(text: impl Into<String>) -> Self {
+        Notice::new(Level::Info, text)
+    }
+
+    #[inline]
+    pub fn warn(text: impl Into<String>) -> Self {
+        Notice::new(Level::Warn, text)
+    }
+
+    #[inline]
+    pub fn error(text: impl Into<String>) -> Self {
+        Notice::new(Level::Error, text)
+    }
+
+    #[inline]
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn is_expired(&self) -> bool {
+        let duration = match self.level {
+            Level::Error => ERROR_DURATION,
+            _ => NOTICE_DURATION,
+        };
+        self.shown.elapsed() >= duration
+    }
+}
+
+/// The bottom line of the screen, showing the latest notice until it expires.
+#[derive(Default)]
+pub struct StatusBar {
+    notice: Option<Notice>,
+}
+
+impl StatusBar {
+    /// Show `notice` in place of the current one.
+    pub fn push(&mut self, notice: Notice) {
+        log::trace!("{}", notice.text());
+        self.notice = Some(notice);
+    }
+
+    #[inline]
+    pub fn notice(&self) -> Option<&Notice> {
+        self.notice.as_ref()
+    }
+
+    /// Clear the notice once it has been shown for long enough.
+    pub fn expire(&mut self) {
+        if self.notice.as_ref().is_some_and(Notice::is_expired) {
+            self.notice = None;
+        }
+    }
+}
@@ -0,0 +1,337 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2024 Mohammed Rehaan and contributors
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ * */
+
+use std::rc::Rc;
+use std::sync::LazyLock;
+
+use chrono::format::StrftimeItems;
+use chrono::{
+    DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone,
+    Utc,
+};
+use regex::{Captures, Regex};
+
+use crate::utils::strip_ansi;
+
+// Lines without a timestamp looked at before giving up on detecting the format.
+const DETECT_LINES: usize = 256;
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+static ISO: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(\d{4})-(\d{2})-(\d{2})[T ](\d{2}):(\d{2}):(\d{2})(?:[.,](\d{1,9}))?",
+        r"\s?(Z|[+-]\d{2}:?\d{2})?",
+    ))
+    .unwrap()
+});
+static SYSLOG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b([A-Z][a-z]{2}) {1,2}(\d{1,2}) (\d{2}):(\d{2}):(\d{2})(?:\.(\d{1,9}))?\b")
+        .unwrap()
+});
+static CLF: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[(\d{2})/([A-Z][a-z]{2})/(\d{4}):(\d{2}):(\d{2}):(\d{2}) ([+-]\d{4})\]").unwrap()
+});
+static EPOCH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[?(\d{13}|\d{10})(?:\.(\d{1,9}))?\b").unwrap());
+
+/// Ways a timestamp can be written on a line. Times are given in milliseconds
+/// since the unix epoch, those without an offset are taken as UTC so they still
+/// order correctly against each other. Along with the time comes the offset it was
+/// written in, which times typed in by the user are read in as well.
+#[derive(Clone)]
+pub enum TimeFormat {
+    /// RFC 3339 and ISO 8601 like `2026-10-17T14:32:05.123Z`.
+    Iso,
+    /// Syslog like `Oct 17 14:32:05`, in the current year.
+    Syslog,
+    /// Apache common log format like `[17/Oct/2026:14:32:05 +0000]`.
+    Clf,
+    /// Seconds or milliseconds since the unix epoch at the start of the line.
+    Epoch,
+    /// A `strftime` format matched at the start of the line.
+    Custom(Rc<str>),
+}
+
+// Tried in this order, the looser formats last.
+const DETECTED: [TimeFormat; 4] = [
+    TimeFormat::Iso,
+    TimeFormat::Clf,
+    TimeFormat::Syslog,
+    TimeFormat::Epoch,
+];
+
+impl TimeFormat {
+    /// Check a `strftime` format before it is used as a custom one.
+    pub fn custom(format: &str) -> anyhow::Result<Self> {
+        StrftimeItems::new(format).parse()?;
+        Ok(TimeFormat::Custom(format.into()))
+    }
+
+    /// The time of the first timestamp in the line written this way.
+    pub fn parse(&self, text: &str) -> Option<i64> {
+        self.stamp(text).map(|(time, _)| time)
+    }
+
+    /// The time of the first timestamp in the line written this way and its offset.
+    /// Timestamps in seconds since the epoch are shown in the local offset.
+    pub fn stamp(&self, text: &str) -> Option<(i64, FixedOffset)> {
+        let text = strip_ansi(text);
+        match self {
+            TimeFormat::Iso => iso_millis(&ISO.captures(&text)?),
+            TimeFormat::Syslog => syslog_millis(&SYSLOG.captures(&text)?),
+            TimeFormat::Clf => clf_millis(&CLF.captures(&text)?),
+            TimeFormat::Epoch => epoch_millis(&EPOCH.captures(&text)?),
+            TimeFormat::Custom(format) => custom_millis(text.trim_start(), format),
+        }
+    }
+}
+
+/// Finds the timestamps on the lines of a file. The format is detected from
+/// the first line with a timestamp and kept for the file, a custom format is
+/// tried before the common ones.
+#[derive(Default)]
+pub struct TimeParser {
+    custom: Option<TimeFormat>,
+    format: Option<TimeFormat>,
+    // Lines looked at without detecting a format.
+    misses: usize,
+}
+
+impl TimeParser {
+    pub fn new(custom: Option<TimeFormat>) -> Self {
+        TimeParser {
+            custom,
+            format: None,
+            misses: 0,
+        }
+    }
+
+    /// The time of the timestamp on the line, if it has one.
+    pub fn parse(&mut self, text: &str) -> Option<i64> {
+        self.stamp(text).map(|(time, _)| time)
+    }
+
+    /// The time of the timestamp on the line and the offset it was written in.
+    pub fn stamp(&mut self, text: &str) -> Option<(i64, FixedOffset)> {
+        if let Some(format) = self.format.as_ref() {
+            return format.stamp(text);
+        }
+        if self.misses >= DETECT_LINES {
+            return None;
+        }
+
+        for format in self.custom.iter().cloned().chain(DETECTED) {
+            if let Some(stamp) = format.stamp(text) {
+                self.format = Some(format);
+                return Some(stamp);
+            }
+        }
+        self.misses += 1;
+        None
+    }
+}
+
+fn num(caps: &Captures, i: usize) -> Option<u32> {
+    caps.get(i)?.as_str().parse().ok()
+}
+
+fn month(name: &str) -> Option<u32> {
+    MONTHS.iter().position(|&m| m == name).map(|i| i as u32 + 1)
+}
+
+// Milliseconds of a fraction of a second given by its digits.
+fn fraction(digits: Option<regex::Match>) -> Option<u32> {
+    let Some(digits) = digits else {
+        return Some(0);
+    };
+    let digits = format!("{:0<3}", &digits.as_str()[..digits.len().min(3)]);
+    digits.parse().ok()
+}
+
+// Offsets like `Z`, `+05:30` or `-0800`.
+fn parse_offset(text: &str) -> Option<FixedOffset> {
+    if text == "Z" {
+        return FixedOffset::east_opt(0);
+    }
+    let sign = if text.starts_with('-') { -1 } else { 1 };
+    let digits = text[1..].replace(':', "");
+    let hours: i32 = digits.get(..2)?.parse().ok()?;
+    let minutes: i32 = digits.get(2..4)?.parse().ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+fn millis(date: NaiveDate, time: NaiveTime, offset: Option<FixedOffset>) -> i64 {
+    let millis = date.and_time(time).and_utc().timestamp_millis();
+    let offset = offset.map_or(0, |offset| offset.local_minus_utc());
+    millis - i64::from(offset) * 1000
+}
+
+fn iso_millis(caps: &Captures) -> Option<(i64, FixedOffset)> {
+    let date = NaiveDate::from_ymd_opt(num(caps, 1)? as i32, num(caps, 2)?, num(caps, 3)?)?;
+    let time = NaiveTime::from_hms_milli_opt(
+        num(caps, 4)?,
+        num(caps, 5)?,
+        num(caps, 6)?,
+        fraction(caps.get(7))?,
+    )?;
+    let offset = match caps.get(8) {
+        Some(offset) => parse_offset(offset.as_str())?,
+        None => Utc.fix(),
+    };
+    Some((millis(date, time, Some(offset)), offset))
+}
+
+fn syslog_millis(caps: &Captures) -> Option<(i64, FixedOffset)> {
+    let year = Utc::now().year();
+    let date = NaiveDate::from_ymd_opt(year, month(&caps[1])?, num(caps, 2)?)?;
+    let time = NaiveTime::from_hms_milli_opt(
+        num(caps, 3)?,
+        num(caps, 4)?,
+        num(caps, 5)?,
+        fraction(caps.get(6))?,
+    )?;
+    Some((millis(date, time, None), Utc.fix()))
+}
+
+fn clf_millis(caps: &Captures) -> Option<(i64, FixedOffset)> {
+    let date = NaiveDate::from_ymd_opt(num(caps, 3)? as i32, month(&caps[2])?, num(caps, 1)?)?;
+    let time = NaiveTime::from_hms_opt(num(caps, 4)?, num(caps, 5)?, num(caps, 6)?)?;
+    let offset = parse_offset(&caps[7])?;
+    Some((millis(date, time, Some(offset)), offset))
+}
+
+fn epoch_millis(caps: &Captures) -> Option<(i64, FixedOffset)> {
+    let whole: i64 = caps[1].parse().ok()?;
+    let millis = match caps[1].len() {
+        13 => whole,
+        _ => whole * 1000 + i64::from(fraction(caps.get(2))?),
+    };
+    let offset = Local.timestamp_millis_opt(millis).single()?.offset().fix();
+    Some((millis, offset))
+}
+
+// Formats without an offset are taken as UTC, and without a date as the first day.
+fn custom_millis(text: &str, format: &str) -> Option<(i64, FixedOffset)> {
+    if let Ok((time, _)) = DateTime::parse_and_remainder(text, format) {
+        return Some((time.timestamp_millis(), *time.offset()));
+    }
+    if let Ok((time, _)) = NaiveDateTime::parse_and_remainder(text, format) {
+        return Some((time.and_utc().timestamp_millis(), Utc.fix()));
+    }
+    let (time, _) = NaiveTime::parse_and_remainder(text, format).ok()?;
+    Some((millis(NaiveDate::default(), time, None), Utc.fix()))
+}
+
+/// A time typed in by the user, like `14:32:05`, `2026-10-17T14:32` or `2026-10-17`.
+/// Times without an offset are read in `offset`, the one the timestamps in view are
+/// written in, and a time of day alone is taken on the same day there as `day`.
+pub fn parse_input(input: &str, day: i64, offset: FixedOffset) -> Option<i64> {
+    let input = input.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Some(time.timestamp_millis());
+    }
+
+    for format in [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(input, format) {
+            return Some(millis(time.date(), time.time(), Some(offset)));
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return Some(millis(date, NaiveTime::MIN, Some(offset)));
+    }
+
+    let date = DateTime::from_timestamp_millis(day)?
+        .with_timezone(&offset)
+        .date_naive();
+    for format in ["%H:%M:%S%.f", "%H:%M"] {
+        if let Ok(time) = NaiveTime::parse_from_str(input, format) {
+            return Some(millis(date, time, Some(offset)));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offset(hours: i32) -> FixedOffset {
+        FixedOffset::east_opt(hours * 3600).unwrap()
+    }
+
+    fn utc(text: &str) -> i64 {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    #[test]
+    fn stamps_keep_their_offset() {
+        let line = "2026-10-17T14:32:05+02:00 started";
+        let stamp = TimeFormat::Iso.stamp(line);
+        assert_eq!(stamp, Some((utc("2026-10-17T12:32:05Z"), offset(2))));
+
+        let line = "2026-10-17 14:32:05 started";
+        let stamp = TimeFormat::Iso.stamp(line);
+        assert_eq!(stamp, Some((utc("2026-10-17T14:32:05Z"), offset(0))));
+    }
+
+    #[test]
+    fn time_of_day_is_read_in_the_offset_of_the_log() {
+        let day = utc("2026-10-17T12:32:05Z");
+        let time = parse_input("14:32:05", day, offset(2));
+        assert_eq!(time, Some(utc("2026-10-17T14:32:05+02:00")));
+    }
+
+    #[test]
+    fn time_of_day_is_taken_on_the_day_of_the_log() {
+        // Still the 17th at -02:00 although it is already the 18th in UTC.
+        let day = utc("2026-10-17T23:30:00-02:00");
+        let time = parse_input("23:45", day, offset(-2));
+        assert_eq!(time, Some(utc("2026-10-17T23:45:00-02:00")));
+    }
+
+    #[test]
+    fn dates_are_read_in_the_offset_of_the_log() {
+        let time = parse_input("2026-10-17 14:32", 0, offset(2));
+        assert_eq!(time, Some(utc("2026-10-17T14:32:00+02:00")));
+
+        let time = parse_input("2026-10-17", 0, offset(2));
+        assert_eq!(time, Some(utc("2026-10-17T00:00:00+02:00")));
+    }
+
+    #[test]
+    fn explicit_offsets_win() {
+        let time = parse_input("2026-10-17T14:32:05Z", 0, offset(2));
+        assert_eq!(time, Some(utc("2026-10-17T14:32:05Z")));
+    }
+}
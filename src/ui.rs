@@ -23,10 +23,17 @@
  *
  * */
 
+use ansi_to_tui::IntoText;
 use ratatui::{border, prelude::*, widgets::*};
+use regex::Regex;
 
 use crate::cursor::*;
+use crate::input::Prompt;
+use crate::io::FileList;
 use crate::resource::*;
+use crate::search::SearchMode;
+use crate::status::Level;
+use crate::utils::{group_digits, split_sgr};
 use crate::RectVec;
 
 const BORDER: BorderType = BorderType::Thick;
@@ -56,6 +63,28 @@ const LOG_MSG: Style = Style {
     add_modifier: Modifier::empty(),
     sub_modifier: Modifier::empty(),
 };
+const MARKER: Style = Style {
+    fg: Some(Color::Yellow),
+    bg: Some(RBG),
+    underline_color: None,
+    add_modifier: Modifier::ITALIC,
+    sub_modifier: Modifier::empty(),
+};
+const HIGHLIGHT: Style = Style {
+    fg: Some(RBG),
+    bg: Some(Color::Yellow),
+    underline_color: None,
+    add_modifier: Modifier::empty(),
+    sub_modifier: Modifier::empty(),
+};
+// Lines of a watched command that changed since its previous run, like `watch -d`.
+const CHANGED: Style = Style {
+    fg: None,
+    bg: None,
+    underline_color: None,
+    add_modifier: Modifier::REVERSED,
+    sub_modifier: Modifier::empty(),
+};
 const INVISIBLE: Style = Style {
     fg: None,
     bg: None,
@@ -74,22 +103,38 @@ const FADE: Style = Style {
 const HEADERS: &str = "\
     (ctrl) + (q) quit  │  \
     (ctrl) + (n) new file  │  \
-    (ctrl) + (d) delete file  │  \
+    (ctrl) + (d) delete file / half page down  │  \
+    (ctrl) + (u) half page up  │  \
+    (ctrl) + (f) / (b) page down / up  │  \
     (ctrl) + (j) top  │  \
     (ctrl) + (k) bottom  │  \
     (ctrl) + (h) cursor at files | \
     (ctrl) + (l) cursor at text | \
-    (ctrl) + (t) toggle tailing";
+    (ctrl) + (t) toggle tailing | \
+    (ctrl) + (a) toggle colors | \
+    (/) search | \
+    (&) filter | \
+    (@) jump to time | \
+    (:) go to line | \
+    (space) mark file | \
+    (m) merge marked files | \
+    (r) reopen | \
+    (n) / (N) next / previous match";
 
 pub fn ui(frame: &mut Frame, res: &mut Resource) {
     let size = frame.size();
 
     let vflex = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(3), Constraint::Percentage(100)])
+        .constraints([
+            Constraint::Min(3),
+            Constraint::Percentage(100),
+            Constraint::Length(1),
+        ])
         .split(size);
 
-    ui_main(frame, vflex, res);
+    ui_main(frame, vflex.clone(), res);
+    ui_status_bar(frame, vflex[2], res);
 }
 
 fn ui_main(frame: &mut Frame, vflex: RectVec, res: &mut Resource) {
@@ -117,6 +162,69 @@ fn ui_header(frame: &mut Frame, fflex: Rect) {
     );
 }
 
+// What the keys currently do, along with anything that changes how they act.
+fn get_mode_summary(res: &Resource) -> String {
+    let mode = match res.entry_box().is_visible() {
+        true => match res.entry_box().prompt() {
+            Prompt::File => "OPEN FILE",
+            Prompt::Search(_) => "SEARCH",
+            Prompt::Filter(_) => "FILTER",
+            Prompt::Time => "JUMP TO TIME",
+            Prompt::Goto => "GO TO LINE",
+        },
+        false if res.pointer().cursor_at::<Files>() => "FILES",
+        false => "VIEW",
+    };
+    let mut summary = format!(" {mode} ");
+    if let Some(count) = res.count() {
+        summary += &format!("· count {count} ");
+    }
+    let marked = res.files().iter().filter(|f| f.is_marked()).count();
+    if marked > 0 {
+        summary += &format!("· {marked} marked ");
+    }
+    let curr_index = res.file_list_state().index();
+    if let Some(curr_buff) = res.files().get_file_buff(curr_index) {
+        if let Some(filter) = curr_buff.filter() {
+            summary += &format!("· filter {} ", filter.expr());
+        }
+        if !curr_buff.colors() {
+            summary += "· colors off ";
+        }
+    }
+    summary
+}
+
+fn ui_status_bar(frame: &mut Frame, sflex: Rect, res: &Resource) {
+    let summary = get_mode_summary(res);
+    let width = (summary.chars().count() as u16).min(sflex.width);
+    let sflex = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(width), Constraint::Min(0)])
+        .split(sflex);
+
+    frame.render_widget(
+        Paragraph::new(summary).style(Style::default().fg(RBG).bg(RFG)),
+        sflex[0],
+    );
+
+    let notice = match res.status().notice() {
+        Some(notice) => {
+            let style = match notice.level() {
+                Level::Info => BLOCK,
+                Level::Warn => MARKER,
+                Level::Error => ERR,
+            };
+            Line::styled(format!(" {} ", notice.text()), style)
+        }
+        None => Line::default(),
+    };
+    frame.render_widget(
+        Paragraph::new(notice).style(Style::default().bg(RBG).fg(RFG)),
+        sflex[1],
+    );
+}
+
 #[inline]
 fn get_cursor_shade_from_condition(cond: bool) -> Style {
     if cond {
@@ -126,19 +234,108 @@ fn get_cursor_shade_from_condition(cond: bool) -> Style {
     }
 }
 
-fn get_lines_from_buffer(res: &Resource, hflex: Rect) -> Vec<Line> {
+fn get_lines_from_buffer(res: &Resource, hflex: Rect) -> Vec<Line<'_>> {
     let curr_index = res.file_list_state().index();
     let Some(curr_buff) = res.files().get_file_buff(curr_index) else {
         return Default::default(); // Return default
     };
     let (buffer, nulled) = curr_buff.buffer(hflex);
+    let regex = curr_buff.search().map(|search| search.regex());
     buffer
-        .into_iter()
-        .map(|l| Line::styled(l, if nulled { LOG_MSG } else { BLOCK }))
+        .iter()
+        .map(|line| match line.number() {
+            _ if nulled => Line::styled(line.text(), LOG_MSG),
+            Some(number) => {
+                let label = match curr_buff.is_relative() {
+                    true => format!("+{number}"),
+                    false => number.to_string(),
+                };
+                let mut spans = vec![Span::raw(format!("{label:>6}|  "))];
+                let mut text = get_ansi_spans(line.text(), curr_buff.colors());
+                if curr_buff.is_changed(number) {
+                    for span in text.iter_mut() {
+                        span.style = span.style.patch(CHANGED);
+                    }
+                }
+                match regex {
+                    Some(regex) => spans.extend(highlight_matches(text, regex)),
+                    None => spans.extend(text),
+                }
+                Line::from(spans).style(BLOCK)
+            }
+            None => Line::styled(format!("{:>6}   {}", "", line.text()), MARKER),
+        })
         .collect::<Vec<Line>>()
 }
 
+// Turn SGR escape sequences into styled spans, or drop them when colors are off.
+fn get_ansi_spans(text: &str, colors: bool) -> Vec<Span<'static>> {
+    let Ok(parsed) = split_sgr(text).as_bytes().into_text() else {
+        return vec![Span::raw(text.to_string())];
+    };
+    let spans = parsed.lines.into_iter().flat_map(|line| line.spans);
+    if colors {
+        return spans.map(without_reset).collect();
+    }
+    vec![Span::raw(
+        spans.map(|span| span.content).collect::<String>(),
+    )]
+}
+
+// Split the spans along the matches of the search so those can be highlighted.
+fn highlight_matches(spans: Vec<Span<'static>>, regex: &Regex) -> Vec<Span<'static>> {
+    let plain = spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect::<String>();
+    let ranges = regex
+        .find_iter(&plain)
+        .map(|found| found.range())
+        .filter(|range| !range.is_empty())
+        .collect::<Vec<_>>();
+    if ranges.is_empty() {
+        return spans;
+    }
+
+    let mut split = Vec::new();
+    let mut offset = 0;
+    for span in spans {
+        let (start, end) = (offset, offset + span.content.len());
+        offset = end;
+
+        let mut cut = start;
+        for range in ranges.iter().filter(|r| r.start < end && r.end > start) {
+            let (from, to) = (range.start.max(start), range.end.min(end));
+            if from > cut {
+                let text = span.content[cut - start..from - start].to_string();
+                split.push(Span::styled(text, span.style));
+            }
+            let text = span.content[from - start..to - start].to_string();
+            split.push(Span::styled(text, span.style.patch(HIGHLIGHT)));
+            cut = to;
+        }
+        if cut < end {
+            split.push(Span::styled(
+                span.content[cut - start..].to_string(),
+                span.style,
+            ));
+        }
+    }
+    split
+}
+
+// A reset goes back to the colors of the text pane rather than the terminal's.
+fn without_reset(mut span: Span<'static>) -> Span<'static> {
+    let style = &mut span.style;
+    style.fg = style.fg.filter(|&color| color != Color::Reset);
+    style.bg = style.bg.filter(|&color| color != Color::Reset);
+    style.underline_color = style.underline_color.filter(|&color| color != Color::Reset);
+    span
+}
+
 fn ui_main_frame(frame: &mut Frame, hflex: RectVec, res: &mut Resource) {
+    res.panes.files = hflex[0];
+    res.panes.text = hflex[1];
     ui_list_box_main(frame, hflex[0], res);
     ui_text_main(frame, hflex[1], res);
 }
@@ -160,6 +357,53 @@ fn ui_list_box_main(frame: &mut Frame, hflex: Rect, res: &mut Resource) {
     ui_list_box(frame, hflex, res);
 }
 
+fn get_text_title(res: &Resource, hflex: Rect) -> String {
+    let curr_index = res.file_list_state().index();
+    let Some(curr_buff) = res.files().get_file_buff(curr_index) else {
+        return String::new();
+    };
+    let mut title = format!(" {} ", curr_buff.name());
+    let (_, nulled) = curr_buff.buffer(hflex);
+    let (first, last, total) = curr_buff.view_range();
+    if curr_buff.is_waiting() {
+        title += "· waiting for file… ";
+    } else if first > last && !nulled {
+        title += &format!("· {} lines ", group_digits(curr_buff.line_count()));
+    } else if !nulled {
+        let percent = last * 100 / total.max(1);
+        title += &format!(
+            "· line {}–{} of {} ({percent}%) ",
+            group_digits(first),
+            group_digits(last),
+            group_digits(total)
+        );
+    }
+    if curr_buff.is_loading() {
+        title += "· loading… ";
+    }
+    if curr_buff.is_tail() {
+        title += "· TAIL ";
+    }
+    if let Some(status) = curr_buff.exit_status() {
+        title += &format!("· {status} ");
+    }
+    if let Some(interval) = curr_buff.watch_interval() {
+        title += &format!("· every {interval:?} ");
+    }
+    if let Some(line) = curr_buff.pending_line() {
+        title += &format!("· going to line {line}… ");
+    }
+    if let Some((current, total, done)) = curr_buff.search_status() {
+        let more = if done { "" } else { "+" };
+        title += &match current {
+            Some(current) => format!("· match {current} of {total}{more} "),
+            None if total == 0 && done => "· no matches ".to_string(),
+            None => format!("· {total}{more} matches "),
+        };
+    }
+    title
+}
+
 fn ui_text_main(frame: &mut Frame, hflex: Rect, res: &mut Resource) {
     let cursor = res.pointer();
 
@@ -168,6 +412,8 @@ fn ui_text_main(frame: &mut Frame, hflex: Rect, res: &mut Resource) {
             .block(
                 Block::default()
                     .borders(border!(ALL))
+                    .title(get_text_title(res, hflex))
+                    .title_alignment(Alignment::Center)
                     .border_style(get_cursor_shade_from_condition(cursor.cursor_at::<View>()))
                     .border_type(BORDER)
                     .style(Style::default().bg(RBG).fg(RFG)),
@@ -175,19 +421,53 @@ fn ui_text_main(frame: &mut Frame, hflex: Rect, res: &mut Resource) {
             .wrap(Wrap { trim: false }),
         hflex,
     );
+    ui_text_scrollbar(frame, hflex, res);
+}
+
+// Draw the thumb over the right border, leaving the border itself as the track.
+fn ui_text_scrollbar(frame: &mut Frame, hflex: Rect, res: &Resource) {
+    let curr_index = res.file_list_state().index();
+    let Some(curr_buff) = res.files().get_file_buff(curr_index) else {
+        return;
+    };
+    let (len, height) = (curr_buff.len(), curr_buff.height());
+    if len <= height {
+        return;
+    }
+    let mut state = ScrollbarState::new(len - height + 1)
+        .position(curr_buff.position())
+        .viewport_content_length(height);
+    frame.render_stateful_widget(
+        Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .track_symbol(None)
+            .thumb_style(get_cursor_shade_from_condition(
+                res.pointer().cursor_at::<View>(),
+            )),
+        hflex.inner(&Margin {
+            vertical: 1,
+            horizontal: 0,
+        }),
+        &mut state,
+    );
 }
 
-fn get_list<'a>(items: &[&'a str]) -> List<'a> {
-    List::new(get_list_items(items))
+fn get_list(items: Vec<ListItem<'static>>) -> List<'static> {
+    List::new(items)
         .block(Block::default().border_style(INVISIBLE))
         .highlight_symbol(" ► ")
         .highlight_style(Style::default().fg(RBG).bg(RFG))
 }
 
-fn get_list_items<'a>(items: &[&'a str]) -> Vec<ListItem<'a>> {
-    items
+// Files that do not exist yet are greyed out.
+fn get_list_items(files: &FileList) -> Vec<ListItem<'static>> {
+    files
         .iter()
-        .map(|&i| ListItem::new(i))
+        .map(|file| match file.is_waiting() {
+            true => ListItem::new(format!("{} · waiting for file…", file.label())).style(FADE),
+            false => ListItem::new(file.label()),
+        })
         .collect::<Vec<ListItem>>()
 }
 
@@ -198,8 +478,9 @@ fn ui_list_box(frame: &mut Frame, hflex: Rect, res: &mut Resource) {
         .constraints([Constraint::Percentage(100), Constraint::Min(3)])
         .split(hflex);
 
-    let list_items = res.files().names();
-    let list = get_list(&list_items);
+    let list_items = get_list_items(res.files());
+    let list = get_list(list_items);
+    res.panes.list = lflex[0];
 
     frame.render_stateful_widget(list, lflex[0], res.file_list_state_mut().get_mut());
 
@@ -215,6 +496,15 @@ fn ui_entry_box(frame: &mut Frame, lflex: Rect, res: &mut Resource) {
     let len = res.entry_box().len().min(width);
 
     let is_err = res.entry_box().is_err();
+    let title = match res.entry_box().prompt() {
+        Prompt::File => " Filename or !command ",
+        Prompt::Search(SearchMode::Regex) => " Search (regex) ",
+        Prompt::Search(SearchMode::Literal) => " Search (literal) ",
+        Prompt::Filter(SearchMode::Regex) => " Filter (regex) ",
+        Prompt::Filter(SearchMode::Literal) => " Filter (literal) ",
+        Prompt::Time => " Jump to time ",
+        Prompt::Goto => " Go to line ",
+    };
 
     let entry_text = res.entry_box().get_span(width);
     let entry_box = Paragraph::new(entry_text)
@@ -222,7 +512,7 @@ fn ui_entry_box(frame: &mut Frame, lflex: Rect, res: &mut Resource) {
             Block::default()
                 .borders(border!(ALL))
                 .border_style([BLOCK, ERR][is_err as usize])
-                .title(" Filename ")
+                .title(title)
                 .title_alignment(Alignment::Left)
                 .border_type(BORDER),
         )
@@ -23,6 +23,11 @@
  *
  * */
 
+use std::borrow::Cow;
+use std::time::Duration;
+
+use crate::io::TAIL_LINES;
+
 ///Returns greatest common prefix of two strings
 fn gcp(s1: String, s2: String) -> String {
     let mut idx = 0;
@@ -44,3 +49,117 @@ pub fn complete(mut filenames: Vec<String>, prefix: &String) -> String {
         .reduce(gcp)
         .unwrap_or(prefix.to_string())
 }
+
+///Splits a leading `-n <N>` or `-e` off the entry box input
+///Returns the number of lines to start before the end of the file with and the remaining path
+pub fn split_lines_arg(input: &str) -> (Option<usize>, &str) {
+    if let Some(path) = input.strip_prefix("-e ") {
+        return (Some(TAIL_LINES), path.trim_start());
+    }
+    let Some(rest) = input.strip_prefix("-n ") else {
+        return (None, input);
+    };
+    let rest = rest.trim_start();
+    let (count, path) = rest.split_once(' ').unwrap_or((rest, ""));
+    match count.parse() {
+        Ok(count) => (Some(count), path.trim_start()),
+        Err(_) => (None, input),
+    }
+}
+
+///Splits a leading `-w <SECONDS>` off the entry box input
+///Returns how often to run the command again and the remaining input
+pub fn split_watch_arg(input: &str) -> (Option<Duration>, &str) {
+    let Some(rest) = input.strip_prefix("-w ") else {
+        return (None, input);
+    };
+    let rest = rest.trim_start();
+    let (seconds, command) = rest.split_once(' ').unwrap_or((rest, ""));
+    match seconds.parse().map(Duration::try_from_secs_f64) {
+        Ok(Ok(interval)) => (Some(interval), command.trim_start()),
+        _ => (None, input),
+    }
+}
+
+///Splits SGR escape sequences with several parameters into one sequence per parameter
+///`ansi-to-tui` ignores sequences like `ESC[1;31m` but handles `ESC[1mESC[31m` just fine
+pub fn split_sgr(text: &str) -> Cow<'_, str> {
+    if !text.contains('\x1b') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut split = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find("\x1b[") {
+        split.push_str(&rest[..pos]);
+        rest = &rest[pos + 2..];
+
+        let end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == ';'))
+            .unwrap_or(rest.len());
+        if !rest[end..].starts_with('m') {
+            split.push_str("\x1b[");
+            continue;
+        }
+
+        let params: Vec<&str> = rest[..end].split(';').collect();
+        let mut idx = 0;
+        while idx < params.len() {
+            // Extended colors take their arguments along.
+            let len = match (params[idx], params.get(idx + 1)) {
+                ("38" | "48" | "58", Some(&"5")) => 3,
+                ("38" | "48" | "58", Some(&"2")) => 5,
+                _ => 1,
+            };
+            let group = &params[idx..(idx + len).min(params.len())];
+            match group.join(";").as_str() {
+                "" => split.push_str("\x1b[0m"),
+                group => split.push_str(&format!("\x1b[{group}m")),
+            }
+            idx += len;
+        }
+        rest = &rest[end + 1..];
+    }
+    split.push_str(rest);
+
+    Cow::Owned(split)
+}
+
+///Removes ANSI escape sequences, leaving the text that is shown
+pub fn strip_ansi(text: &str) -> Cow<'_, str> {
+    if !text.contains('\x1b') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+            continue;
+        }
+        // CSI sequences end with a letter, anything else is a single character escape.
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        }
+    }
+
+    Cow::Owned(stripped)
+}
+
+///Formats a number with commas between groups of three digits
+pub fn group_digits(number: usize) -> String {
+    let digits = number.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
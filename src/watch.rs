@@ -0,0 +1,151 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2024 Mohammed Rehaan and contributors
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ * */
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Reports which open files changed on disk so only those are read again.
+///
+/// Parent directories are watched rather than the files themselves so that
+/// rotation, deletion and re-creation are noticed as well. Native events
+/// (inotify on Linux) are used where available, falling back to polling for
+/// paths the native backend refuses, e.g. when the inotify watch limit is hit.
+pub struct FileWatcher {
+    sender: Sender<notify::Result<Event>>,
+    events: Receiver<notify::Result<Event>>,
+    native: Option<RecommendedWatcher>,
+    poll: Option<PollWatcher>,
+    // Watched directories with the number of open files inside them and
+    // whether the polling fallback is used for them.
+    dirs: HashMap<PathBuf, (usize, bool)>,
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        let (sender, events) = channel();
+
+        let native = notify::recommended_watcher(sender.clone())
+            .map_err(|err| log::trace!("Falling back to polling for file changes: {err}"))
+            .ok();
+
+        FileWatcher {
+            sender,
+            events,
+            native,
+            poll: None,
+            dirs: HashMap::new(),
+        }
+    }
+
+    pub fn watch(&mut self, path: &Path) {
+        let Some(dir) = path.parent() else {
+            return;
+        };
+        if let Some((count, _)) = self.dirs.get_mut(dir) {
+            *count += 1;
+            return;
+        }
+
+        let is_native = self
+            .native
+            .as_mut()
+            .map(|native| native.watch(dir, RecursiveMode::NonRecursive))
+            .is_some_and(|res| res.is_ok());
+
+        if !is_native {
+            let res = self
+                .poll_watcher()
+                .and_then(|poll| Ok(poll.watch(dir, RecursiveMode::NonRecursive)?));
+            if let Err(err) = res {
+                log::trace!("Couldn't watch {} due to: {err}", dir.display());
+                return;
+            }
+        }
+
+        self.dirs.insert(dir.to_path_buf(), (1, !is_native));
+    }
+
+    pub fn unwatch(&mut self, path: &Path) {
+        let Some(dir) = path.parent() else {
+            return;
+        };
+        let Some((count, is_polled)) = self.dirs.get_mut(dir) else {
+            return;
+        };
+
+        *count -= 1;
+        if *count > 0 {
+            return;
+        }
+
+        let res = match (*is_polled, self.native.as_mut(), self.poll.as_mut()) {
+            (false, Some(native), _) => native.unwatch(dir),
+            (true, _, Some(poll)) => poll.unwatch(dir),
+            _ => Ok(()),
+        };
+        if let Err(err) = res {
+            log::trace!("Couldn't unwatch {} due to: {err}", dir.display());
+        }
+
+        self.dirs.remove(dir);
+    }
+
+    /// Drain the pending events and return the paths they touched.
+    pub fn changed(&self) -> Vec<PathBuf> {
+        self.events
+            .try_iter()
+            .filter_map(|event| {
+                event
+                    .map_err(|err| log::trace!("File watcher error: {err}"))
+                    .ok()
+            })
+            .filter(|event| !matches!(event.kind, EventKind::Access(_)))
+            .flat_map(|event| event.paths)
+            .collect()
+    }
+
+    fn poll_watcher(&mut self) -> anyhow::Result<&mut PollWatcher> {
+        let poll = match self.poll.take() {
+            Some(poll) => poll,
+            None => {
+                let config = Config::default().with_poll_interval(POLL_INTERVAL);
+                PollWatcher::new(self.sender.clone(), config)?
+            }
+        };
+        Ok(self.poll.insert(poll))
+    }
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        FileWatcher::new()
+    }
+}